@@ -3,17 +3,17 @@
 #![no_main]
 #![no_std]
 
-use core::panic::PanicInfo;
 use embedded_graphics::{
     pixelcolor::{raw::RawU16, Rgb565, RgbColor},
     prelude::*,
 };
-use embedded_hal::digital::v2::{InputPin, OutputPin};
+use embedded_hal::digital::v2::OutputPin;
 use embedded_hal::prelude::_embedded_hal_adc_OneShot;
 use embedded_hal::spi::MODE_0;
 use msp430::interrupt;
 use msp430_rt::entry;
 use msp430fr2355::{E_USCI_B1};
+use msp430fr2355_boosterpack::buttons::{ButtonPin, Buttons, EventKind, Timing};
 use msp430fr2355_boosterpack::serial_utils::*;
 use msp430fr2x5x_hal::{
     adc::*,
@@ -27,24 +27,12 @@ use msp430fr2x5x_hal::{
 };
 use st7735_lcd::ST7735;
 
-#[panic_handler]
-fn panic(_info: &PanicInfo) -> ! {
-    // Disable interrupts to prevent further damage.
-    msp430::interrupt::disable();
-    if let Some(location) = _info.location() {
-        print_bytes(b"Panic occurred in file ");
-        print_bytes(location.file().as_bytes());
-        print_bytes(b" at line ");
-        print_bytes(&u32_to_dec(location.line()));
-        print_bytes(b"\n");
-    } else {
-        print_bytes(b"Panic handler was called, something bad happened.\n");
-    }
-    loop {
-        // Prevent optimizations that can remove this loop.
-        msp430::asm::barrier();
-    }
-}
+// TB0CTL: ACLK, continuous mode, cleared
+const TBSSEL_ACLK: u16 = 0b01 << 8;
+const MC_CONTINUOUS: u16 = 0b10 << 4;
+const TBCLR: u16 = 1 << 2;
+// 5 ms of the 32768 Hz ACLK, the tick period Timing::default() is meant for
+const BUTTON_TICK: u16 = 164;
 
 #[entry]
 fn main() -> ! {
@@ -67,8 +55,14 @@ fn main() -> ! {
         let p6 = Batch::new(periph.P6).split(&pmm);
 
         let mut led_g_pin = p6.pin6.to_output();
-        let btn_1_pin = p2.pin4.pulldown();
-        // let btn_2_pin = p3.pin3.pulldown();  // hardware issue on launchpad - always low for some reason, even when off?
+        // BoosterPack S1 and S2 have external pull-ups, a pull-down holds them low
+        let btn_1_pin = ButtonPin::active_low(p2.pin4.pullup());
+        let btn_2_pin = ButtonPin::active_low(p3.pin3.pullup());
+        let mut buttons: Buttons<2> = Buttons::new(Timing::default());
+        // The loop takes a varying time, so buttons are ticked off a free-running timer instead
+        let button_timer = periph.TB0;
+        button_timer.tb0ctl.write(|w| unsafe{w.bits(TBSSEL_ACLK | MC_CONTINUOUS | TBCLR)});
+        let mut last_tick = 0u16;
 
         let (tx, rx) = SerialConfig::new(
             periph.E_USCI_A1,
@@ -137,12 +131,18 @@ fn main() -> ! {
             .ok();
         screen.clear(Rgb565::BLACK).unwrap();
 
-        let mut btn_1_prev = false;
-
         loop {
-            let btn_1 = btn_1_pin.is_low().unwrap();
-            let btn_1_pressed = btn_1 && !btn_1_prev;
-            btn_1_prev = btn_1;
+            // At most one tick per iteration, on a fresh sample. Ticks missed during a slow
+            // iteration are dropped rather than replayed, which only stretches the timings.
+            let now = button_timer.tb0r.read().bits();
+            if now.wrapping_sub(last_tick) >= BUTTON_TICK {
+                last_tick = now;
+                buttons.tick([btn_1_pin.is_down(), btn_2_pin.is_down()]);
+            }
+            let mut btn_1_pressed = false;
+            while let Some(event) = buttons.next_event() {
+                btn_1_pressed |= event.button == 0 && event.kind == EventKind::Press;
+            }
 
             let joy_x: u16 = adc.read(&mut joystick_x_pin).unwrap();
             let mut joy_y: u16 = adc.read(&mut joystick_y_pin).unwrap();
@@ -171,8 +171,3 @@ fn main() -> ! {
     }
     loop {}
 }
-
-#[no_mangle]
-extern "C" fn abort() -> ! {
-    panic!();
-}
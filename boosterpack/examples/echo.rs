@@ -1,10 +1,11 @@
 #![no_main]
 #![no_std]
 
-use core::panic::PanicInfo;
 use embedded_hal::digital::v2::*;
 use embedded_hal::prelude::*;
 use msp430_rt::entry;
+// Only linked for its panic handler
+use msp430fr2355_boosterpack as _;
 use msp430fr2x5x_hal::{
     clock::{ClockConfig, DcoclkFreqSel, MclkDiv, SmclkDiv},
     fram::Fram,
@@ -14,15 +15,6 @@ use msp430fr2x5x_hal::{
     watchdog::Wdt,
 };
 
-#[panic_handler]
-fn panic(_info: &PanicInfo) -> ! {
-    // Disable interrupts to prevent further damage.
-    msp430::interrupt::disable();
-    loop {
-        msp430::asm::barrier();
-    }
-}
-
 // Prints "HELLO" when started then echos on UART1
 // Serial settings are listed in the code
 #[entry]
@@ -65,11 +57,3 @@ fn main() -> ! {
         loop {}
     }
 }
-
-// The compiler will emit calls to the abort() compiler intrinsic if debug assertions are
-// enabled (default for dev profile). MSP430 does not actually have meaningful abort() support
-// so for now, we create our own in each application where debug assertions are present.
-#[no_mangle]
-extern "C" fn abort() -> ! {
-    panic!();
-}
@@ -3,7 +3,6 @@
 #![no_main]
 #![no_std]
 
-use core::panic::PanicInfo;
 use embedded_hal::digital::v2::OutputPin;
 use embedded_hal::prelude::_embedded_hal_adc_OneShot;
 use msp430_rt::entry;
@@ -18,25 +17,6 @@ use msp430fr2x5x_hal::{
     watchdog::Wdt,
 };
 
-#[panic_handler]
-fn panic(_info: &PanicInfo) -> ! {
-    // Disable interrupts to prevent further damage.
-    msp430::interrupt::disable();
-    if let Some(location) = _info.location() {
-        print_bytes(b"Panic occurred in file ");
-        print_bytes(location.file().as_bytes());
-        print_bytes(b" at line ");
-        print_bytes(&u32_to_dec(location.line()));
-        print_bytes(b"\n");
-    } else {
-        print_bytes(b"Panic handler was called, something bad happened.\n");
-    }
-    loop {
-        // Prevent optimizations that can remove this loop.
-        msp430::asm::barrier();
-    }
-}
-
 #[entry]
 fn main() -> ! {
     if let Some(periph) = msp430fr2355::Peripherals::take() {
@@ -104,8 +84,3 @@ fn main() -> ! {
     }
     loop {}
 }
-
-#[no_mangle]
-extern "C" fn abort() -> ! {
-    panic!();
-}
@@ -12,47 +12,36 @@ use msp430fr2x5x_hal::{
     gpio::Batch,
     pmm::Pmm,
     serial::*,
-    watchdog::Wdt,
+    watchdog::{Wdt, WdtClkPeriods},
     i2c::*,
 };
-use core::panic::PanicInfo;
 use msp430fr2355_boosterpack::{
-    opt3001::DeviceOpt3001,
-    serial_utils::{print_bytes, u32_to_dec, byte_to_dec}
+    opt3001::{self, DeviceOpt3001},
+    reset::{self, ResetCause},
+    serial_utils::byte_to_dec,
+    supervisor::{self, Supervisor},
+    sputln, info, error,
 };
 use msp430fr2355_boosterpack::serial_utils::init_serial;
 
 
-#[panic_handler]
-fn panic(_info: &PanicInfo) -> ! {
-    // Disable interrupts to prevent further damage.
-    msp430::interrupt::disable();
-    if let Some(location) = _info.location() {
-        print_bytes(b"Panic occurred in file ");
-        print_bytes(location.file().as_bytes());
-        print_bytes(b" at line ");
-        print_bytes(&u32_to_dec(location.line()));
-        print_bytes(b"\n");
-    } else {
-        print_bytes(b"Panic handler was called, something bad happened.\n");
-    }
-    loop {
-        // Prevent optimizations that can remove this loop.
-        msp430::asm::barrier();
-    }
-}
-
 #[entry]
 fn main() -> ! {
 
     if let Some(periph) = msp430fr2355::Peripherals::take() {
+        reset::init();
         let mut fram = Fram::new(periph.FRCTL);
-        let _wdt = Wdt::constrain(periph.WDT_A);
+        let mut wdt = Wdt::constrain(periph.WDT_A);
         let (_smclk, aclk, mut delay) = ClockConfig::new(periph.CS)
             .mclk_dcoclk(DcoclkFreqSel::_1MHz, MclkDiv::_1)
             .smclk_on(SmclkDiv::_2)
             .aclk_refoclk()
             .freeze(&mut fram);
+        // 16 s at 32768 Hz
+        wdt.set_aclk(&aclk).start(WdtClkPeriods::_512K);
+        let mut supervisor: Supervisor<_, 1> = Supervisor::new(wdt);
+        // Polled every second
+        let sensor_task = supervisor.add_task("sensor", 3).unwrap();
 
         let pmm = Pmm::new(periph.PMM);
         let p4 = Batch::new(periph.P4).split(&pmm);
@@ -72,7 +61,14 @@ fn main() -> ! {
         init_serial(rx, tx);
 
 
-        print_bytes(b"Serial started\n\nConfiguring USCI B0 for I2C...\n");
+        info!("Serial started");
+        reset::log_cause();
+        if reset::cause() == Some(ResetCause::Watchdog) {
+            if let Some(overdue) = supervisor::last_overdue() {
+                overdue.print();
+            }
+        }
+        info!("Configuring USCI B0 for I2C...");
 
         // P1.3 SCL, P1.2 SDA
         let p1 = Batch::new(periph.P1).split(&pmm);
@@ -80,44 +76,48 @@ fn main() -> ! {
         config.use_smclk(&_smclk, 5);// ~100 MHz
         let periph_i2c : SDL<E_USCI_B0> = config.sdl(p1.pin3.to_alternate1(), p1.pin2.to_alternate1());
 
-        print_bytes(b"I2C peripheral configured\n\nConfiguring opt3001 sensor...\n");
+        info!("I2C peripheral configured");
+        info!("Configuring opt3001 sensor...");
 
 
-        let mut device : DeviceOpt3001<E_USCI_B0>;
+        let mut device : DeviceOpt3001<SDL<E_USCI_B0>>;
         match DeviceOpt3001::new(periph_i2c){
             Ok(dev) =>  {
                 device = dev;
-                print_bytes(b"Configuration successful\n\n");
-                print_bytes(b"Polling from device...\n");
+                info!("Configuration successful");
+                info!("Polling from device...");
                 loop {
                     match device.read_light() {
                         Ok(res) =>  {
-                            print_bytes(b"lux: ");
-                            print_bytes(&u32_to_dec(res.whole)[3..=8]);
-                            print_bytes(b".");
-                            print_bytes(&byte_to_dec(res.frac)[1..=2]);
-                            print_bytes(b"\n");
+                            sputln!("lux: ", res.whole, ".", &byte_to_dec(res.frac)[1..=2]);
+                            sensor_task.check_in();
                         },
                         _ => {
-                            print_bytes(b"Read failed\n");
+                            error!("Read failed");
                             break;
                         }
                     }
+                    supervisor.tick().ok();
                     delay.delay_ms(1000u16);
                 }
             },
-            Err(I2CErr::GotNACK) => {
-                print_bytes(b"Configuration failed: got NACK response\n\n");
+            Err(opt3001::Error::I2c(I2CErr::GotNACK)) => {
+                error!("Configuration failed: got NACK response");
+            },
+            Err(opt3001::Error::WrongManufacturer(id) | opt3001::Error::WrongDevice(id)) => {
+                error!("Configuration failed: not an OPT3001, read ID 0x{:x}", id);
             },
             _ => {
-                print_bytes(b"Configuration failed\n\n");
+                error!("Configuration failed");
             }
         };
+        // The failure was reported once above. Keep feeding the watchdog, or it would reset the
+        // device every 16 s and overwrite the reset cause and overdue record printed at startup.
+        loop {
+            sensor_task.check_in();
+            supervisor.tick().ok();
+            delay.delay_ms(1000u16);
+        }
     }
     loop {}
-}
-
-#[no_mangle]
-extern "C" fn abort() -> ! {
-    panic!();
 }
\ No newline at end of file
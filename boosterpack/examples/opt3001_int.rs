@@ -0,0 +1,118 @@
+//! OPT3001 end-of-conversion interrupts on the INT pin
+//!
+//! The sensor converts continuously and pulls INT low after each conversion. INT is on
+//! BoosterPack pin J1.8, which is P3.4 on the LaunchPad. Its port interrupt only notes the
+//! event, and the main loop reads the result when one is pending.
+
+#![no_main]
+#![no_std]
+
+use msp430::interrupt::{enable as enable_interrupts, CriticalSection};
+use msp430_rt::entry;
+use msp430fr2355::{interrupt, E_USCI_B0, P3};
+use msp430fr2x5x_hal::{
+    clock::{ClockConfig, DcoclkFreqSel, MclkDiv, SmclkDiv},
+    fram::Fram,
+    gpio::Batch,
+    i2c::*,
+    pmm::Pmm,
+    serial::*,
+    watchdog::Wdt,
+};
+use msp430fr2355_boosterpack::{
+    opt3001::{self, Address, ConversionTime, DeviceOpt3001, Mode},
+    serial_utils::{byte_to_dec, init_serial},
+    sputln, info, error,
+};
+
+// P3.4 in the P3IES/P3IFG/P3IE registers
+const INT_PIN: u8 = 1 << 4;
+
+#[entry]
+fn main() -> ! {
+    if let Some(periph) = msp430fr2355::Peripherals::take() {
+        let mut fram = Fram::new(periph.FRCTL);
+        let _wdt = Wdt::constrain(periph.WDT_A);
+        let (smclk, aclk, _delay) = ClockConfig::new(periph.CS)
+            .mclk_dcoclk(DcoclkFreqSel::_1MHz, MclkDiv::_1)
+            .smclk_on(SmclkDiv::_2)
+            .aclk_refoclk()
+            .freeze(&mut fram);
+
+        let pmm = Pmm::new(periph.PMM);
+        let p1 = Batch::new(periph.P1).split(&pmm);
+        let p3 = Batch::new(periph.P3).split(&pmm);
+        let p4 = Batch::new(periph.P4).split(&pmm);
+        let (tx, rx) = SerialConfig::new(
+            periph.E_USCI_A1,
+            BitOrder::LsbFirst,
+            BitCount::EightBits,
+            StopBits::OneStopBit,
+            Parity::NoParity,
+            Loopback::NoLoop,
+            9600,
+        )
+        .use_aclk(&aclk)
+        .split(p4.pin3.to_alternate1(), p4.pin2.to_alternate1());
+        init_serial(rx, tx);
+
+        // P1.3 SCL, P1.2 SDA
+        let mut config: I2CBusConfig<E_USCI_B0> = I2CBusConfig::new(periph.E_USCI_B0);
+        config.use_smclk(&smclk, 5);
+        let bus: SDL<E_USCI_B0> = config.sdl(p1.pin3.to_alternate1(), p1.pin2.to_alternate1());
+
+        let mut device = match DeviceOpt3001::new(bus) {
+            Ok(device) => device,
+            Err(_) => {
+                error!("OPT3001 not responding");
+                loop {}
+            }
+        };
+        // The default configuration latches INT active low, until the configuration is read
+        let setup = device.set_end_of_conversion_mode()
+            .and_then(|()| device.set_conversion_time(ConversionTime::Ms800))
+            .and_then(|()| device.set_mode(Mode::Continuous));
+        if setup.is_err() {
+            error!("OPT3001 configuration failed");
+            loop {}
+        }
+
+        // INT is open drain, so pull it up and interrupt when it goes low. Changing the edge can
+        // set the flag, so it is cleared before enabling the interrupt.
+        let _int_pin = p3.pin4.pullup();
+        let port = unsafe{&*P3::ptr()};
+        port.p3ies.modify(|r, w| unsafe{w.bits(r.bits() | INT_PIN)});
+        port.p3ifg.modify(|r, w| unsafe{w.bits(r.bits() & !INT_PIN)});
+        port.p3ie.modify(|r, w| unsafe{w.bits(r.bits() | INT_PIN)});
+        unsafe {
+            enable_interrupts();
+        }
+        info!("Waiting for conversions");
+
+        loop {
+            if !opt3001::take_int(Address::Gnd) {
+                continue;
+            }
+            // Reading the status reads the configuration, which releases INT for the next one
+            let lux = match device.status() {
+                Ok(status) if status.conversion_ready => device.read_light(),
+                Ok(_) => continue,
+                Err(err) => Err(err),
+            };
+            match lux {
+                Ok(lux) => sputln!("lux: ", lux.whole, ".", &byte_to_dec(lux.frac)[1..=2]),
+                Err(_) => error!("Read failed"),
+            }
+        }
+    }
+    loop {}
+}
+
+#[interrupt]
+fn PORT3(_cs: CriticalSection) {
+    // Reading P3IV clears the highest pending flag, INT being the only one enabled
+    let port = unsafe{&*P3::ptr()};
+    if port.p3iv.read().bits() != 0 {
+        opt3001::on_int_interrupt(Address::Gnd);
+    }
+}
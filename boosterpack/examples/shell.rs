@@ -0,0 +1,236 @@
+//! Interactive command shell for bringing up a board over the LaunchPad's USB serial port
+
+#![no_main]
+#![no_std]
+
+use embedded_graphics::{
+    pixelcolor::{raw::RawU16, Rgb565, RgbColor},
+    prelude::*,
+};
+use embedded_hal::blocking::i2c::WriteRead;
+use embedded_hal::prelude::_embedded_hal_adc_OneShot;
+use embedded_hal::spi::MODE_0;
+use msp430::interrupt;
+use msp430_rt::entry;
+use msp430fr2355::{E_USCI_B0, E_USCI_B1};
+use msp430fr2355_boosterpack::{
+    crash,
+    reset,
+    opt3001::{Address, Config, DeviceOpt3001},
+    serial_utils::*,
+    shell::{Args, Command, Shell, ShellError},
+    sput,
+};
+use msp430fr2x5x_hal::{
+    adc::*,
+    clock::{ClockConfig, DcoclkFreqSel, MclkDiv, SmclkDiv},
+    fram::Fram,
+    gpio::{Alternate3, Batch, Floating, Input, Output, Pin, Pin0, Pin2, Pin5, P1, P3, P4, P5},
+    i2c::{I2CBusConfig, SDL},
+    pmm::Pmm,
+    serial::*,
+    spi::{SPIBusConfig, SPIPins},
+    watchdog::Wdt,
+};
+use st7735_lcd::ST7735;
+
+type Screen = ST7735<SPIPins<E_USCI_B1>, Pin<P3, Pin2, Output>, Pin<P4, Pin0, Output>>;
+
+/// The OPT3001's I2C bus, wrapped in the driver once the sensor has answered
+enum Light {
+    Bus(SDL<E_USCI_B0>),
+    Sensor(DeviceOpt3001<SDL<E_USCI_B0>>),
+}
+
+impl Light {
+    fn into_bus(self) -> SDL<E_USCI_B0> {
+        match self {
+            Light::Bus(bus) => bus,
+            Light::Sensor(dev) => dev.release(),
+        }
+    }
+
+    // Check and configure the sensor if that hasn't been done yet, keeping the bus if it fails
+    fn into_sensor(self) -> Light {
+        match self {
+            Light::Bus(bus) => {
+                let mut dev = DeviceOpt3001::new_unverified(bus, Address::Gnd);
+                match dev.verify().and_then(|()| dev.set_config(Config::default())) {
+                    Ok(()) => Light::Sensor(dev),
+                    Err(_) => Light::Bus(dev.release()),
+                }
+            }
+            sensor => sensor,
+        }
+    }
+}
+
+/// Everything the commands can touch
+struct Ctx {
+    /// Only `None` while a command is switching it between the bus and the driver
+    light: Option<Light>,
+    screen: Screen,
+    adc: Adc,
+    joystick_x: Pin<P1, Pin5, Alternate3<Input<Floating>>>,
+    joystick_y: Pin<P5, Pin0, Alternate3<Input<Floating>>>,
+}
+
+static COMMANDS: &[Command<Ctx>] = &[
+    Command { name: "lux", usage: "lux", help: "read the OPT3001 light sensor", run: lux },
+    Command { name: "adc", usage: "adc <ch>", help: "read ADC channel 5 or 8 (joystick x/y)", run: adc },
+    Command { name: "lcd", usage: "lcd clear <color>", help: "fill the screen, by name or RGB565 value", run: lcd },
+    Command { name: "i2c", usage: "i2c scan", help: "list responding I2C addresses", run: i2c },
+    Command::gpio(),
+    Command::peek(),
+    Command::poke(),
+    Command::crash(),
+];
+
+fn lux(ctx: &mut Ctx, _args: &mut Args) -> Result<(), ShellError> {
+    let mut light = ctx.light.take().map(Light::into_sensor);
+    let res = match light.as_mut() {
+        Some(Light::Sensor(dev)) => dev.read_light().map_err(|_| ShellError::Failed),
+        _ => Err(ShellError::Failed),
+    };
+    ctx.light = light;
+    let res = res?;
+    sput!(res.whole, ".", &byte_to_dec(res.frac)[1..=2], " lux\r\n");
+    Ok(())
+}
+
+fn adc(ctx: &mut Ctx, args: &mut Args) -> Result<(), ShellError> {
+    let result: u16 = match args.next_u8()? {
+        5 => ctx.adc.read(&mut ctx.joystick_x),
+        8 => ctx.adc.read(&mut ctx.joystick_y),
+        _ => return Err(ShellError::BadArg),
+    }
+    .map_err(|_| ShellError::Failed)?;
+    sput!(result, "\r\n");
+    Ok(())
+}
+
+fn lcd(ctx: &mut Ctx, args: &mut Args) -> Result<(), ShellError> {
+    if args.next_str()? != "clear" {
+        return Err(ShellError::BadArg);
+    }
+    let color = match args.next_str()? {
+        "black" => Rgb565::BLACK,
+        "white" => Rgb565::WHITE,
+        "red" => Rgb565::RED,
+        "green" => Rgb565::GREEN,
+        "blue" => Rgb565::BLUE,
+        "yellow" => Rgb565::YELLOW,
+        "cyan" => Rgb565::CYAN,
+        "magenta" => Rgb565::MAGENTA,
+        raw => {
+            let raw = msp430fr2355_boosterpack::shell::parse_u32(raw)
+                .and_then(|val| u16::try_from(val).ok())
+                .ok_or(ShellError::BadArg)?;
+            Rgb565::from(RawU16::new(raw))
+        }
+    };
+    ctx.screen.clear(color).map_err(|_| ShellError::Failed)
+}
+
+fn i2c(ctx: &mut Ctx, args: &mut Args) -> Result<(), ShellError> {
+    if args.next_str()? != "scan" {
+        return Err(ShellError::BadArg);
+    }
+    // Works whether or not the sensor answered, `lux` sets it up again when next used
+    let mut bus = ctx.light.take().ok_or(ShellError::Failed)?.into_bus();
+    // Reserved addresses at either end are skipped. Probe by reading register 0, as the
+    // eUSCI_B master doesn't reliably finish zero-length writes.
+    for addr in 0x08u8..0x78 {
+        let mut buf = [0u8; 1];
+        if bus.write_read(addr, &[0], &mut buf).is_ok() {
+            sput!("0x", byte_to_hex(addr), "\r\n");
+        }
+    }
+    ctx.light = Some(Light::Bus(bus));
+    Ok(())
+}
+
+#[entry]
+fn main() -> ! {
+    if let Some(periph) = msp430fr2355::Peripherals::take() {
+        reset::init();
+        let mut fram = Fram::new(periph.FRCTL);
+        let _wdt = Wdt::constrain(periph.WDT_A);
+        let (smclk, aclk, mut delay) = ClockConfig::new(periph.CS)
+            .mclk_dcoclk(DcoclkFreqSel::_8MHz, MclkDiv::_1)
+            .smclk_on(SmclkDiv::_1)
+            .aclk_refoclk()
+            .freeze(&mut fram);
+
+        let pmm = Pmm::new(periph.PMM);
+        let p1 = Batch::new(periph.P1).split(&pmm);
+        let p3 = Batch::new(periph.P3).split(&pmm);
+        let p4 = Batch::new(periph.P4).split(&pmm);
+        let p5 = Batch::new(periph.P5).split(&pmm);
+
+        let (tx, rx) = SerialConfig::new(
+            periph.E_USCI_A1,
+            BitOrder::LsbFirst,
+            BitCount::EightBits,
+            StopBits::OneStopBit,
+            Parity::NoParity,
+            Loopback::NoLoop,
+            9600,
+        )
+        .use_aclk(&aclk)
+        .split(p4.pin3.to_alternate1(), p4.pin2.to_alternate1());
+        init_serial(rx, tx);
+
+        // P1.3 SCL, P1.2 SDA
+        let mut i2c_config: I2CBusConfig<E_USCI_B0> = I2CBusConfig::new(periph.E_USCI_B0);
+        i2c_config.use_smclk(&smclk, 80); // ~100 kHz
+        let i2c_bus: SDL<E_USCI_B0> = i2c_config.sdl(p1.pin3.to_alternate1(), p1.pin2.to_alternate1());
+        let light = Light::Bus(i2c_bus).into_sensor();
+        if let Light::Bus(_) = light {
+            print_bytes(b"OPT3001 not responding\n");
+        }
+
+        // ADC Channel 5 (P1.5) = Joystick X Axis
+        // ADC Channel 8 (P5.0) = Joystick Y Axis
+        let joystick_x = p1.pin5.to_alternate3();
+        let joystick_y = p5.pin0.to_alternate3();
+        let adc = AdcConfig::new(
+            periph.ADC,
+            ClockSource::MODCLK,
+            ClockDivider::_1,
+            Predivider::_1,
+            Resolution::_10BIT,
+            SamplingRate::_200KSPS,
+            SampleTime::_4,
+        )
+        .config_hw();
+
+        // P4.7: MISO, P4.6: MOSI, P4.5: SCLK, P4.4: CS, P3.2: rs, P4.0: rst
+        let mut spi_config: SPIBusConfig<E_USCI_B1> =
+            SPIBusConfig::new(periph.E_USCI_B1, MODE_0, true);
+        spi_config.use_smclk(&smclk, 1);
+        let periph_spi: SPIPins<E_USCI_B1> = spi_config.spi_pins(
+            p4.pin7.to_alternate1(),
+            p4.pin6.to_alternate1(),
+            p4.pin5.to_alternate1(),
+            p4.pin4.to_alternate1(),
+        );
+        let mut screen = ST7735::new(periph_spi, p3.pin2.to_output(), p4.pin0.to_output(), false, false, 128, 128);
+        screen.init(&mut delay).ok();
+        screen.set_offset(2, 3);
+        screen.set_orientation(&st7735_lcd::Orientation::PortraitSwapped).ok();
+        screen.clear(Rgb565::BLACK).ok();
+
+        unsafe {
+            interrupt::enable();
+        }
+        print_bytes(b"\nBoosterPack shell, type 'help' for commands\n");
+        reset::log_cause();
+        crash::record_boot();
+        crash::report();
+
+        let mut ctx = Ctx { light: Some(light), screen, adc, joystick_x, joystick_y };
+        Shell::new(COMMANDS).run(&mut ctx);
+    }
+    loop {}
+}
@@ -3,7 +3,6 @@
 #![no_main]
 #![no_std]
 
-use core::panic::PanicInfo;
 use embedded_hal::prelude::{
     _embedded_hal_blocking_delay_DelayMs
 };
@@ -33,17 +32,6 @@ use st7735_lcd::ST7735;
 use msp430fr2355_boosterpack::stream::request_stream;
 
 
-#[panic_handler]
-fn panic(_info: &PanicInfo) -> ! {
-    msp430::interrupt::disable();
-    print_bytes(b"Panic handler was called.\n");
-    loop {
-        // Prevent optimizations that can remove this loop.
-        msp430::asm::barrier();
-    }
-}
-
-
 #[entry]
 fn main() -> ! {
     if let Some(periph) = msp430fr2355::Peripherals::take() {
@@ -68,7 +56,7 @@ fn main() -> ! {
         )
         .use_smclk(&smclk)
         .split(p4.pin3.to_alternate1(), p4.pin2.to_alternate1());
-        init_serial(rx, tx);
+        let console = init_serial(rx, tx);
 
         print_bytes(b"Serial started\n\nConfiguring USCI B1 for SPI...\n");
 
@@ -101,21 +89,21 @@ fn main() -> ! {
                 screen.set_orientation(&st7735_lcd::Orientation::PortraitSwapped).ok();
                 screen.clear(Rgb565::BLACK).ok();
                 print_bytes(b"Screen initialized.\n");
-                let num_imgs = stream::get_num_images();
+                let num_imgs = stream::get_num_images(console);
                 print_bytes(&serial_utils::u16_to_hex(num_imgs));
                 print_bytes(b" images available.\nGetting images...\n");
                 for idx in 0u16 .. num_imgs{
                     print_bytes(b"get img: ");
                     print_bytes(&serial_utils::u16_to_hex(idx));
                     print_bytes(b"\n");
-                    stream::request_img(idx, &mut screen);
+                    stream::request_img(console, idx, &mut screen);
                     delay.delay_ms(100u16);
                 }
                 delay.delay_ms(2000u16);
                 print_bytes(b"Image transfer complete\n");
                 screen.clear(Rgb565::BLACK).ok();
                 loop {
-                    request_stream(&mut screen);
+                    request_stream(console, &mut screen);
                     delay.delay_ms(10u16);
                 }
             }
@@ -126,27 +114,3 @@ fn main() -> ! {
     }
     loop{}
 }
-
-#[no_mangle]
-extern "C" fn abort() -> ! {
-    panic!();
-}
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
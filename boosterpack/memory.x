@@ -1,6 +1,22 @@
 MEMORY
 {
+  INFOMEM : ORIGIN = 0x1800, LENGTH = 0x200
   RAM : ORIGIN = 0x2000, LENGTH = 0x1000
   ROM : ORIGIN = 0x8000, LENGTH = 0x7F80
   VECTORS : ORIGIN = 0xFFA4, LENGTH = 0x5C
 }
+
+SECTIONS
+{
+  /* Format strings for binary logging (see src/log.rs), kept in the ELF file but not loaded */
+  .blog 1 (INFO) :
+  {
+    KEEP(*(.blog .blog.*));
+  }
+
+  /* Values kept across resets (see src/persist.rs), never loaded or initialized */
+  .persist (NOLOAD) :
+  {
+    KEEP(*(.persist .persist.*));
+  } > INFOMEM
+}
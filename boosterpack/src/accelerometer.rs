@@ -0,0 +1,217 @@
+//! Driver for the BoosterPack's KXTC9-2050 analog accelerometer
+//!
+//! Each axis is an analog voltage centered on half the supply, changing by about 660 mV/g at
+//! 3.3 V. The axes are wired to BoosterPack pins J3.23 (X), J3.24 (Y) and J3.25 (Z); configure the
+//! LaunchPad pins behind them as ADC inputs (`to_alternate3()`) and pass them to
+//! [`Accelerometer::new`]. The ADC itself is only borrowed for each reading, so it can be shared
+//! with the joystick:
+//! ```ignore
+//! let mut accel = Accelerometer::new(x_pin, y_pin, z_pin, Calibration::for_resolution(10));
+//! let a = accel.read(&mut adc)?;
+//! if accel.orientation(&mut adc)? == Orientation::FaceDown { /* ... */ }
+//! ```
+use core::marker::PhantomData;
+use embedded_hal::adc::{Channel, OneShot};
+
+/// Acceleration in thousandths of a g, positive towards the sensor's axis arrows
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Acceleration {
+    pub x: i16,
+    pub y: i16,
+    pub z: i16,
+}
+
+/// Converts ADC readings to acceleration
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Calibration {
+    /// Reading at 0 g, for X, Y and Z
+    pub zero_g: [u16; 3],
+    /// Change in reading per g, for X, Y and Z
+    pub counts_per_g: [u16; 3],
+}
+
+impl Calibration {
+    /// Datasheet values for an ADC with a 3.3 V reference and `bits` of resolution:
+    /// 0 g at half scale, 1 g being a fifth of full scale
+    pub const fn for_resolution(bits: u8) -> Self {
+        let full_scale = 1u16 << bits;
+        Calibration {
+            zero_g: [full_scale / 2; 3],
+            counts_per_g: [full_scale / 5; 3],
+        }
+    }
+}
+
+/// Which way the board is facing, decided by the axis closest to vertical
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Orientation {
+    /// Lying flat, components up
+    FaceUp,
+    /// Lying flat, components down
+    FaceDown,
+    /// Standing on its bottom edge (+Y up)
+    PortraitUp,
+    /// Standing on its top edge (-Y up)
+    PortraitDown,
+    /// Standing on its right edge (+X up)
+    LandscapeLeft,
+    /// Standing on its left edge (-X up)
+    LandscapeRight,
+    /// No axis clearly vertical, e.g. near 45 degrees, moving or falling
+    Unknown,
+}
+
+/// Below this, in milli-g, no axis is considered vertical
+const ORIENTATION_MIN_MG: i16 = 500;
+
+/// For reading the accelerometer through ADC pins `X`, `Y` and `Z`
+pub struct Accelerometer<ADC, X, Y, Z> {
+    x: X,
+    y: Y,
+    z: Z,
+    calibration: Calibration,
+    _adc: PhantomData<ADC>,
+}
+
+impl<ADC, X, Y, Z> Accelerometer<ADC, X, Y, Z>
+where
+    X: Channel<ADC>,
+    Y: Channel<ADC>,
+    Z: Channel<ADC>,
+{
+    pub fn new(x: X, y: Y, z: Z, calibration: Calibration) -> Self {
+        Accelerometer {
+            x,
+            y,
+            z,
+            calibration,
+            _adc: PhantomData,
+        }
+    }
+
+    pub fn calibration(&self) -> Calibration {
+        self.calibration
+    }
+
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.calibration = calibration;
+    }
+
+    /// Blocking read of the three ADC channels, X first
+    pub fn read_raw<A, E>(&mut self, adc: &mut A) -> Result<[u16; 3], E>
+    where
+        A: OneShot<ADC, u16, X, Error = E> + OneShot<ADC, u16, Y, Error = E> + OneShot<ADC, u16, Z, Error = E>,
+    {
+        let x = nb::block!(adc.read(&mut self.x))?;
+        let y = nb::block!(adc.read(&mut self.y))?;
+        let z = nb::block!(adc.read(&mut self.z))?;
+        Ok([x, y, z])
+    }
+
+    /// Blocking read, converted with the calibration
+    pub fn read<A, E>(&mut self, adc: &mut A) -> Result<Acceleration, E>
+    where
+        A: OneShot<ADC, u16, X, Error = E> + OneShot<ADC, u16, Y, Error = E> + OneShot<ADC, u16, Z, Error = E>,
+    {
+        let raw = self.read_raw(adc)?;
+        Ok(self.convert(raw))
+    }
+
+    /// Convert readings from [`read_raw`](Self::read_raw) to milli-g
+    pub fn convert(&self, raw: [u16; 3]) -> Acceleration {
+        let axis = |i: usize| {
+            let counts = raw[i] as i32 - self.calibration.zero_g[i] as i32;
+            let mg = counts * 1000 / (self.calibration.counts_per_g[i].max(1) as i32);
+            mg.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+        };
+        Acceleration { x: axis(0), y: axis(1), z: axis(2) }
+    }
+
+    /// Set the zero-g readings from `samples` readings taken while the board lies still and
+    /// flat, face up. Keeps the sensitivity.
+    pub fn calibrate_level<A, E>(&mut self, adc: &mut A, samples: u8) -> Result<Calibration, E>
+    where
+        A: OneShot<ADC, u16, X, Error = E> + OneShot<ADC, u16, Y, Error = E> + OneShot<ADC, u16, Z, Error = E>,
+    {
+        let samples = samples.max(1);
+        let mut sum = [0u32; 3];
+        for _ in 0..samples {
+            let raw = self.read_raw(adc)?;
+            for (total, reading) in sum.iter_mut().zip(raw) {
+                *total += reading as u32;
+            }
+        }
+        let mean = sum.map(|total| (total / samples as u32) as u16);
+        // Z sees 1 g when level
+        let z_zero = mean[2].saturating_sub(self.calibration.counts_per_g[2]);
+        self.calibration.zero_g = [mean[0], mean[1], z_zero];
+        Ok(self.calibration)
+    }
+
+    /// Blocking read of the orientation, see [`Acceleration::orientation`]
+    pub fn orientation<A, E>(&mut self, adc: &mut A) -> Result<Orientation, E>
+    where
+        A: OneShot<ADC, u16, X, Error = E> + OneShot<ADC, u16, Y, Error = E> + OneShot<ADC, u16, Z, Error = E>,
+    {
+        Ok(self.read(adc)?.orientation())
+    }
+
+    /// Give back the pins
+    pub fn release(self) -> (X, Y, Z) {
+        (self.x, self.y, self.z)
+    }
+}
+
+impl Acceleration {
+    /// Rotation about the Y axis in tenths of a degree, positive when +X points up.
+    /// Only meaningful while the board is not otherwise accelerating.
+    pub fn pitch(&self) -> i16 {
+        let yz = (self.y as i32 * self.y as i32 + self.z as i32 * self.z as i32) as u32;
+        atan2_decidegrees(self.x as i32, yz.isqrt() as i32)
+    }
+
+    /// Rotation about the X axis in tenths of a degree, positive when +Y points up,
+    /// up to +-180 degrees when face down
+    pub fn roll(&self) -> i16 {
+        atan2_decidegrees(self.y as i32, self.z as i32)
+    }
+
+    /// The orientation, if one axis sees most of gravity
+    pub fn orientation(&self) -> Orientation {
+        let (ax, ay, az) = (self.x.unsigned_abs(), self.y.unsigned_abs(), self.z.unsigned_abs());
+        let largest = ax.max(ay).max(az);
+        if largest < ORIENTATION_MIN_MG as u16 {
+            return Orientation::Unknown;
+        }
+        if largest == az && az > ax && az > ay {
+            if self.z > 0 { Orientation::FaceUp } else { Orientation::FaceDown }
+        } else if largest == ay && ay > ax {
+            if self.y > 0 { Orientation::PortraitUp } else { Orientation::PortraitDown }
+        } else if largest == ax && ax > ay {
+            if self.x > 0 { Orientation::LandscapeLeft } else { Orientation::LandscapeRight }
+        } else {
+            Orientation::Unknown
+        }
+    }
+}
+
+/// Angle of (x, y) from the X axis in tenths of a degree, -1800 to 1800.
+/// Uses atan(r) ~ 45 r + 15.6 r (1 - r) degrees for 0 <= r <= 1, good to about 0.3 degrees.
+fn atan2_decidegrees(y: i32, x: i32) -> i16 {
+    const ONE: i32 = 1024;
+    if x == 0 && y == 0 {
+        return 0;
+    }
+    let (ax, ay) = (x.abs(), y.abs());
+    // angle in the first octant, of the smaller over the larger component
+    let r = if ax >= ay { ay * ONE / ax } else { ax * ONE / ay };
+    let octant = (450 * r + 156 * r * (ONE - r) / ONE) / ONE;
+    let first_quadrant = if ax >= ay { octant } else { 900 - octant };
+    let angle = match (x >= 0, y >= 0) {
+        (true, true) => first_quadrant,
+        (false, true) => 1800 - first_quadrant,
+        (false, false) => first_quadrant - 1800,
+        (true, false) => -first_quadrant,
+    };
+    angle as i16
+}
@@ -0,0 +1,247 @@
+//! Debounced push buttons with click, double-click and long-press events
+//!
+//! [`Buttons`] samples up to 32 buttons on every [`tick`](Buttons::tick), at a steady rate of
+//! a few milliseconds, and queues [`Event`]s for the application to take with
+//! [`next_event`](Buttons::next_event). All timings in [`Timing`] count ticks.
+//!
+//! The BoosterPack's S1 (P2.4) and S2 (P3.3) have pull-ups on the BoosterPack, so configure them
+//! as floating or pulled-up inputs; pull-downs fight the external resistors. The LaunchPad's own
+//! S1 (P4.1) and S2 (P2.3) need the internal pull-up. All four are low when pressed.
+//!
+//! Ticking from a timer interrupt, with the buttons shared like the serial ports are:
+//! ```ignore
+//! static BUTTONS: Mutex<RefCell<Option<(Buttons<2>, ButtonPin<S1>, ButtonPin<S2>)>>> = ...;
+//!
+//! #[interrupt]
+//! fn TIMER0_B0(cs: CriticalSection) {
+//!     if let Some((buttons, s1, s2)) = BUTTONS.borrow(cs).borrow_mut().as_mut() {
+//!         buttons.tick([s1.is_down(), s2.is_down()]);
+//!     }
+//! }
+//! ```
+use embedded_hal::digital::v2::InputPin;
+use crate::queuebuf::QueueBuf;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EventKind {
+    /// Went down, after debouncing
+    Press,
+    /// Came back up
+    Release,
+    /// Pressed and released once, reported once the double-click window has passed
+    Click,
+    /// Pressed and released twice within the double-click window
+    DoubleClick,
+    /// Held down for the long-press time. No click follows when it is released.
+    LongPress,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Event {
+    /// Index of the button in the array given to [`Buttons::tick`]
+    pub button: u8,
+    pub kind: EventKind,
+}
+
+impl Event {
+    // Packed as button << 3 | kind to fit the byte queue
+    fn to_byte(self) -> u8 {
+        let kind = match self.kind {
+            EventKind::Press => 0,
+            EventKind::Release => 1,
+            EventKind::Click => 2,
+            EventKind::DoubleClick => 3,
+            EventKind::LongPress => 4,
+        };
+        (self.button << 3) | kind
+    }
+
+    fn from_byte(byte: u8) -> Self {
+        let kind = match byte & 0b111 {
+            0 => EventKind::Press,
+            1 => EventKind::Release,
+            2 => EventKind::Click,
+            3 => EventKind::DoubleClick,
+            _ => EventKind::LongPress,
+        };
+        Event { button: byte >> 3, kind }
+    }
+}
+
+/// Durations in ticks
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Timing {
+    /// Ticks a new level has to last to be accepted
+    pub debounce: u8,
+    /// Ticks held down before a [`EventKind::LongPress`]
+    pub long_press: u16,
+    /// Ticks after a release in which a second click makes a double click. 0 turns double clicks
+    /// off, reporting clicks straight away.
+    pub double_click: u16,
+}
+
+impl Default for Timing {
+    /// 20 ms debounce, 1 s long press and 300 ms double-click window, when ticking every 5 ms
+    fn default() -> Self {
+        Timing {
+            debounce: 4,
+            long_press: 200,
+            double_click: 60,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct State {
+    down: bool,
+    // ticks the raw level has differed from `down`
+    bouncing: u8,
+    held: u16,
+    long_sent: bool,
+    clicks: u8,
+    since_release: u16,
+}
+
+/// Debouncer and event queue for `N` buttons, at most 32
+pub struct Buttons<const N: usize> {
+    states: [State; N],
+    timing: Timing,
+    events: QueueBuf<16>,
+    dropped: u16,
+}
+
+impl<const N: usize> Buttons<N> {
+    const FITS_EVENT: () = assert!(N <= 32, "at most 32 buttons");
+
+    pub fn new(timing: Timing) -> Self {
+        let () = Self::FITS_EVENT;
+        Buttons {
+            states: [State::default(); N],
+            timing,
+            events: QueueBuf::new([0; 16]),
+            dropped: 0,
+        }
+    }
+
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.timing = timing;
+    }
+
+    /// Sample every button, `true` meaning pressed. Call at a steady rate.
+    pub fn tick(&mut self, down: [bool; N]) {
+        for (index, &raw) in down.iter().enumerate() {
+            self.update(index as u8, raw);
+        }
+    }
+
+    fn update(&mut self, button: u8, raw: bool) {
+        let timing = self.timing;
+        let state = &mut self.states[button as usize];
+        let mut events: [Option<EventKind>; 2] = [None; 2];
+
+        if raw != state.down {
+            state.bouncing += 1;
+            if state.bouncing >= timing.debounce.max(1) {
+                state.bouncing = 0;
+                state.down = raw;
+                if raw {
+                    events[0] = Some(EventKind::Press);
+                    state.held = 0;
+                    state.long_sent = false;
+                } else {
+                    events[0] = Some(EventKind::Release);
+                    if state.long_sent {
+                        state.clicks = 0;
+                    } else if timing.double_click == 0 {
+                        events[1] = Some(EventKind::Click);
+                    } else if state.clicks == 1 {
+                        events[1] = Some(EventKind::DoubleClick);
+                        state.clicks = 0;
+                    } else {
+                        state.clicks = 1;
+                        state.since_release = 0;
+                    }
+                }
+            }
+        } else {
+            state.bouncing = 0;
+        }
+
+        if state.down {
+            state.held = state.held.saturating_add(1);
+            if !state.long_sent && timing.long_press > 0 && state.held >= timing.long_press {
+                state.long_sent = true;
+                state.clicks = 0;
+                events[1] = Some(EventKind::LongPress);
+            }
+        } else if state.clicks == 1 {
+            state.since_release = state.since_release.saturating_add(1);
+            if state.since_release >= timing.double_click {
+                state.clicks = 0;
+                events[1] = Some(EventKind::Click);
+            }
+        }
+
+        for kind in events.into_iter().flatten() {
+            self.push(Event { button, kind });
+        }
+    }
+
+    fn push(&mut self, event: Event) {
+        if self.events.is_full() {
+            self.dropped = self.dropped.saturating_add(1);
+        } else {
+            self.events.put(event.to_byte());
+        }
+    }
+
+    /// The oldest queued event
+    pub fn next_event(&mut self) -> Option<Event> {
+        if self.events.has_data() {
+            Some(Event::from_byte(self.events.get()))
+        } else {
+            None
+        }
+    }
+
+    /// Debounced state of a button
+    pub fn is_down(&self, button: u8) -> bool {
+        self.states.get(button as usize).is_some_and(|s| s.down)
+    }
+
+    /// Events lost because the queue was full
+    pub fn dropped(&self) -> u16 {
+        self.dropped
+    }
+}
+
+/// A button on an input pin, pressed at the given level
+pub struct ButtonPin<P> {
+    pin: P,
+    active_low: bool,
+}
+
+impl<P: InputPin> ButtonPin<P> {
+    /// Pressed when low, like all the buttons on the LaunchPad and BoosterPack
+    pub fn active_low(pin: P) -> Self {
+        ButtonPin { pin, active_low: true }
+    }
+
+    pub fn active_high(pin: P) -> Self {
+        ButtonPin { pin, active_low: false }
+    }
+
+    /// Raw, undebounced state. A pin that can't be read counts as released.
+    pub fn is_down(&self) -> bool {
+        let level = if self.active_low { self.pin.is_low() } else { self.pin.is_high() };
+        level.unwrap_or(false)
+    }
+
+    pub fn release(self) -> P {
+        self.pin
+    }
+}
@@ -0,0 +1,370 @@
+//! Driver for the BoosterPack's piezo buzzer, with RTTTL melody playback
+//!
+//! The buzzer is on BoosterPack pin J4.40, which is P6.0 on the LaunchPad. Its secondary function
+//! is Timer_B3 output 1, so [`Buzzer`] takes over TB3 and generates the tone in hardware, clocked
+//! from SMCLK. The duty cycle sets an approximate volume; 50% is loudest.
+//!
+//! Melodies use the RTTTL ringtone format, e.g. `"scale:d=8,o=5,b=120:c,d,e,f,g,a,b,c6"`, and
+//! play without blocking when [`MelodyPlayer::tick`] is called regularly, e.g. from a timer
+//! interrupt every millisecond:
+//! ```ignore
+//! let mut buzzer = Buzzer::new(periph.TB3, p6.pin0.to_output().to_alternate1(), &smclk);
+//! let mut player = MelodyPlayer::new(Rtttl::parse(MELODY).unwrap());
+//! // in the 1 ms timer interrupt
+//! if !player.tick(&mut buzzer, 1) {
+//!     // finished
+//! }
+//! ```
+use embedded_hal::blocking::delay::DelayMs;
+use msp430fr2x5x_hal::clock::{Clock, Smclk};
+use msp430fr2x5x_hal::gpio::{Alternate1, Output, Pin, Pin0, P6};
+use crate::pac::TB3;
+
+/// P6.0 in its TB3.1 function
+pub type BuzzerPin = Pin<P6, Pin0, Alternate1<Output>>;
+
+// TB3CTL fields
+const TBSSEL_SMCLK: u16 = 0b10 << 8;
+const ID_SHIFT: u16 = 6;
+const MC_UP: u16 = 0b01 << 4;
+const TBCLR: u16 = 1 << 2;
+// TB3CCTL1 output mode 7, reset at CCR1 and set at CCR0
+const OUTMOD_RESET_SET: u16 = 0b111 << 5;
+
+/// For playing tones on the buzzer
+pub struct Buzzer {
+    timer: TB3,
+    pin: BuzzerPin,
+    smclk_hz: u32,
+    volume: u8,
+}
+
+impl Buzzer {
+    /// Silent until [`tone`](Self::tone) is called. Reads the SMCLK frequency once, so create the
+    /// buzzer again if SMCLK changes.
+    pub fn new(timer: TB3, pin: BuzzerPin, smclk: &Smclk) -> Self {
+        let mut buzzer = Buzzer {
+            timer,
+            pin,
+            smclk_hz: smclk.freq(),
+            volume: 100,
+        };
+        buzzer.stop();
+        buzzer
+    }
+
+    /// Start sounding `freq_hz` until [`stop`](Self::stop) or another tone. 0 Hz is silence.
+    /// Frequencies too low for the timer are raised to the lowest it can make.
+    pub fn tone(&mut self, freq_hz: u16) {
+        if freq_hz == 0 || self.volume == 0 {
+            self.stop();
+            return;
+        }
+        // smallest input divider (1, 2, 4 or 8) that fits the period in 16 bits
+        let mut divider = 0;
+        let mut period = self.smclk_hz / freq_hz as u32;
+        while period > 0x1_0000 && divider < 3 {
+            divider += 1;
+            period = (self.smclk_hz >> divider) / freq_hz as u32;
+        }
+        let period = period.clamp(2, 0x1_0000);
+        let duty = (period * self.volume as u32 / 200).max(1);
+
+        let timer = &self.timer;
+        timer.tb3ctl.write(|w| unsafe{w.bits(TBSSEL_SMCLK | (divider << ID_SHIFT) | TBCLR)});
+        timer.tb3ccr0.write(|w| unsafe{w.bits((period - 1) as u16)});
+        timer.tb3ccr1.write(|w| unsafe{w.bits(duty as u16)});
+        timer.tb3cctl1.write(|w| unsafe{w.bits(OUTMOD_RESET_SET)});
+        timer.tb3ctl.modify(|r, w| unsafe{w.bits(r.bits() | MC_UP)});
+    }
+
+    /// Silence the buzzer and stop the timer. The output is held low.
+    pub fn stop(&mut self) {
+        // output mode 0 drives the OUT bit, which is left clear
+        self.timer.tb3cctl1.write(|w| unsafe{w.bits(0)});
+        self.timer.tb3ctl.write(|w| unsafe{w.bits(TBCLR)});
+    }
+
+    /// Blocking tone for `duration_ms`
+    pub fn play<D: DelayMs<u16>>(&mut self, freq_hz: u16, duration_ms: u16, delay: &mut D) {
+        self.tone(freq_hz);
+        delay.delay_ms(duration_ms);
+        self.stop();
+    }
+
+    /// Approximate loudness from 0 (silent) to 100, by changing the duty cycle from 0 to 50%.
+    /// Takes effect with the next tone.
+    pub fn set_volume(&mut self, volume: u8) {
+        self.volume = volume.min(100);
+    }
+
+    pub fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    /// Stop and give back the timer and pin
+    pub fn release(mut self) -> (TB3, BuzzerPin) {
+        self.stop();
+        (self.timer, self.pin)
+    }
+}
+
+/// A note or rest of a melody
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Note {
+    /// 0 for a rest
+    pub freq_hz: u16,
+    pub duration_ms: u16,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RtttlError {
+    /// The name, settings and notes sections aren't separated by two colons
+    MissingSection,
+    /// A setting in the second section isn't `d=`, `o=` or `b=` with a valid number
+    BadSetting,
+    /// The note starting at this byte offset can't be parsed
+    BadNote(usize),
+}
+
+// Fastest tempo at which a 32nd note still lasts a millisecond
+const MAX_BPM: u16 = (4 * 60_000 / 32) as u16;
+
+// Octave 7, C to B, in Hz. Lower octaves halve these.
+const OCTAVE_7: [u16; 12] = [2093, 2217, 2349, 2489, 2637, 2794, 2960, 3136, 3322, 3520, 3729, 3951];
+
+/// A melody in RTTTL format, `name:d=4,o=6,b=63:notes`. Iterates over its notes.
+#[derive(Clone)]
+pub struct Rtttl<'a> {
+    name: &'a str,
+    default_duration: u8,
+    default_octave: u8,
+    whole_ms: u32,
+    notes: &'a str,
+    offset: usize,
+}
+
+impl<'a> Rtttl<'a> {
+    /// Parse the header and check every note. Missing settings default to `d=4,o=6,b=63`.
+    /// Tempos above 7500 bpm are rejected, as their shortest notes would round to 0 ms.
+    pub fn parse(text: &'a str) -> Result<Self, RtttlError> {
+        let mut sections = text.splitn(3, ':');
+        let name = sections.next().ok_or(RtttlError::MissingSection)?;
+        let settings = sections.next().ok_or(RtttlError::MissingSection)?;
+        let notes = sections.next().ok_or(RtttlError::MissingSection)?;
+
+        let (mut duration, mut octave, mut bpm) = (4, 6, 63);
+        for setting in settings.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (key, value) = setting.split_once('=').ok_or(RtttlError::BadSetting)?;
+            let value: u16 = value.trim().parse().map_err(|_| RtttlError::BadSetting)?;
+            match key.trim() {
+                "d" | "D" if valid_duration(value) => duration = value as u8,
+                "o" | "O" if valid_octave(value) => octave = value as u8,
+                "b" | "B" if (1..=MAX_BPM).contains(&value) => bpm = value,
+                _ => return Err(RtttlError::BadSetting),
+            }
+        }
+
+        let melody = Rtttl {
+            name,
+            default_duration: duration,
+            default_octave: octave,
+            // a beat is a quarter note
+            whole_ms: 4 * 60_000 / bpm as u32,
+            notes,
+            offset: 0,
+        };
+        let mut check = melody.clone();
+        while check.offset < check.notes.len() {
+            let start = check.offset;
+            check.next_note().ok_or(RtttlError::BadNote(start + name.len() + settings.len() + 2))?;
+        }
+        Ok(melody)
+    }
+
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Start again from the first note
+    pub fn rewind(&mut self) {
+        self.offset = 0;
+    }
+
+    // Parse the note at `offset` and move past it. `None` if it is malformed.
+    fn next_note(&mut self) -> Option<Note> {
+        let rest = &self.notes[self.offset..];
+        let (token, consumed) = match rest.find(',') {
+            Some(comma) => (&rest[..comma], comma + 1),
+            None => (rest, rest.len()),
+        };
+        self.offset += consumed;
+        let token = token.trim().as_bytes();
+
+        let mut i = 0;
+        let number = |i: &mut usize| {
+            let mut value: Option<u16> = None;
+            while let Some(&c @ b'0'..=b'9') = token.get(*i) {
+                value = Some(value.unwrap_or(0).checked_mul(10)?.checked_add((c - b'0') as u16)?);
+                *i += 1;
+            }
+            Some(value)
+        };
+
+        let duration = number(&mut i)?.unwrap_or(self.default_duration as u16);
+        if !valid_duration(duration) {
+            return None;
+        }
+        let semitone = match token.get(i)?.to_ascii_lowercase() {
+            b'c' => Some(0),
+            b'd' => Some(2),
+            b'e' => Some(4),
+            b'f' => Some(5),
+            b'g' => Some(7),
+            b'a' => Some(9),
+            b'b' | b'h' => Some(11),
+            b'p' => None,
+            _ => return None,
+        };
+        i += 1;
+        let sharp = token.get(i) == Some(&b'#');
+        if sharp {
+            i += 1;
+        }
+        // the dot may come before or after the octave
+        let mut dotted = token.get(i) == Some(&b'.');
+        if dotted {
+            i += 1;
+        }
+        let octave = number(&mut i)?.unwrap_or(self.default_octave as u16);
+        if !valid_octave(octave) {
+            return None;
+        }
+        if token.get(i) == Some(&b'.') {
+            dotted = true;
+            i += 1;
+        }
+        if i != token.len() {
+            return None;
+        }
+
+        let mut duration_ms = self.whole_ms / duration as u32;
+        if dotted {
+            duration_ms += duration_ms / 2;
+        }
+        let freq_hz = match semitone {
+            Some(semitone) => {
+                let index = semitone + sharp as usize;
+                // B# is the next octave's C
+                let (index, octave) = if index == 12 { (0, octave + 1) } else { (index, octave) };
+                let freq = OCTAVE_7[index] as u32;
+                if octave >= 7 { freq << (octave - 7) } else { (freq + (1 << (6 - octave))) >> (7 - octave) }
+            }
+            None => 0,
+        };
+        Some(Note {
+            freq_hz: freq_hz.min(u16::MAX as u32) as u16,
+            duration_ms: duration_ms.min(u16::MAX as u32) as u16,
+        })
+    }
+}
+
+impl Iterator for Rtttl<'_> {
+    type Item = Note;
+
+    fn next(&mut self) -> Option<Note> {
+        if self.offset >= self.notes.len() {
+            return None;
+        }
+        // checked by parse
+        self.next_note()
+    }
+}
+
+fn valid_duration(duration: u16) -> bool {
+    matches!(duration, 1 | 2 | 4 | 8 | 16 | 32)
+}
+
+fn valid_octave(octave: u16) -> bool {
+    (3..=8).contains(&octave)
+}
+
+/// Plays a melody on a [`Buzzer`] without blocking
+pub struct MelodyPlayer<'a> {
+    melody: Rtttl<'a>,
+    current: Option<Note>,
+    elapsed_ms: u16,
+    gap_ms: u16,
+    // the buzzer was already stopped for the gap at the end of the current note
+    in_gap: bool,
+    looping: bool,
+}
+
+impl<'a> MelodyPlayer<'a> {
+    /// Starts with the first note on the first [`tick`](Self::tick)
+    pub fn new(melody: Rtttl<'a>) -> Self {
+        MelodyPlayer {
+            melody,
+            current: None,
+            elapsed_ms: 0,
+            gap_ms: 10,
+            in_gap: false,
+            looping: false,
+        }
+    }
+
+    /// Silence at the end of each note, so repeated notes are heard separately. 10 ms by default.
+    pub fn set_gap(&mut self, gap_ms: u16) {
+        self.gap_ms = gap_ms;
+    }
+
+    /// Start over when the melody ends, instead of stopping
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    /// Advance by `elapsed_ms`, changing the tone when a note ends. Returns `false` once the
+    /// melody has finished.
+    pub fn tick(&mut self, buzzer: &mut Buzzer, elapsed_ms: u16) -> bool {
+        if let Some(note) = self.current {
+            self.elapsed_ms = self.elapsed_ms.saturating_add(elapsed_ms);
+            if self.elapsed_ms < note.duration_ms {
+                if !self.in_gap && self.elapsed_ms >= note.duration_ms.saturating_sub(self.gap_ms) {
+                    buzzer.stop();
+                    self.in_gap = true;
+                }
+                return true;
+            }
+        }
+        self.elapsed_ms = 0;
+        self.in_gap = false;
+        self.current = self.melody.next();
+        if self.current.is_none() && self.looping {
+            self.melody.rewind();
+            self.current = self.melody.next();
+        }
+        match self.current {
+            Some(note) => {
+                buzzer.tone(note.freq_hz);
+                true
+            }
+            None => {
+                buzzer.stop();
+                false
+            }
+        }
+    }
+
+    /// Silence the buzzer and go back to the start of the melody
+    pub fn stop(&mut self, buzzer: &mut Buzzer) {
+        buzzer.stop();
+        self.melody.rewind();
+        self.current = None;
+        self.elapsed_ms = 0;
+        self.in_gap = false;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.current.is_some()
+    }
+}
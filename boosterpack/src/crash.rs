@@ -0,0 +1,260 @@
+//! Crash records kept in information FRAM, so a panic can be looked into after the board has been
+//! reset, e.g. when it happened away from a PC and the serial output was lost.
+//!
+//! With the `crash-record` feature (default) the library's panic handler saves where the panic
+//! happened, the start of its message, the boot count and the last few log lines. Applications
+//! with their own handler can call [`record_panic`]. The record is replaced by the next crash and
+//! kept until [`clear`] is called.
+//!
+//! At startup call [`record_boot`] to count boots, then [`report`] to print any record left by a
+//! previous run. The shell command [`Command::crash`](crate::shell::Command::crash) does the same
+//! on request.
+//!
+//! Only text log lines are kept, not the frames of the `log-binary` mode.
+
+use core::fmt;
+use core::panic::PanicInfo;
+use crate::persist::{Persistent, Plain};
+use crate::sput;
+
+/// Longest file name kept, longer ones keep their end
+pub const FILE_LEN: usize = 31;
+/// Longest panic message kept
+pub const MESSAGE_LEN: usize = 47;
+/// Number of log lines kept
+pub const LOG_LINES: usize = 4;
+/// Longest log line kept, including the level
+pub const LOG_LINE_LEN: usize = 39;
+
+/// Text cut off at a fixed capacity, which must be below 256
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Text<const N: usize> {
+    len: u8,
+    bytes: [u8; N],
+}
+
+unsafe impl<const N: usize> Plain for Text<N> {}
+
+impl<const N: usize> Text<N> {
+    pub const fn new() -> Self {
+        Text { len: 0, bytes: [0; N] }
+    }
+
+    pub fn as_str(&self) -> &str {
+        let len = (self.len as usize).min(N);
+        core::str::from_utf8(&self.bytes[..len]).unwrap_or("")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Append as much of `s` as fits, returns whether all of it did
+    pub fn push_str(&mut self, s: &str) -> bool {
+        let len = self.len as usize;
+        let mut end = s.len().min(N - len);
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.bytes[len..len + end].copy_from_slice(&s.as_bytes()[..end]);
+        self.len += end as u8;
+        end == s.len()
+    }
+
+    /// Replace the contents with as much of the end of `s` as fits
+    fn set_tail(&mut self, s: &str) {
+        let mut start = s.len().saturating_sub(N);
+        while !s.is_char_boundary(start) {
+            start += 1;
+        }
+        self.len = 0;
+        self.push_str(&s[start..]);
+    }
+}
+
+impl<const N: usize> Default for Text<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Formatting stops with an error once the text is full
+impl<const N: usize> fmt::Write for Text<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.push_str(s) { Ok(()) } else { Err(fmt::Error) }
+    }
+}
+
+/// What was saved about a panic
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CrashRecord {
+    line: u32,
+    column: u16,
+    boot: u16,
+    file: Text<FILE_LEN>,
+    message: Text<MESSAGE_LEN>,
+    log: [Text<LOG_LINE_LEN>; LOG_LINES],
+}
+
+unsafe impl Plain for CrashRecord {}
+
+impl CrashRecord {
+    /// End of the source file name, empty if the location is unknown
+    pub fn file(&self) -> &str {
+        self.file.as_str()
+    }
+
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    pub fn column(&self) -> u16 {
+        self.column
+    }
+
+    /// Start of the panic message
+    pub fn message(&self) -> &str {
+        self.message.as_str()
+    }
+
+    /// Value of [`boot_count`] during the run that crashed
+    pub fn boot(&self) -> u16 {
+        self.boot
+    }
+
+    /// Log lines leading up to the panic, oldest first
+    pub fn log_lines(&self) -> impl Iterator<Item = &str> {
+        self.log.iter().filter(|line| !line.is_empty()).map(Text::as_str)
+    }
+
+    /// Print the record to the default serial console
+    pub fn print(&self) {
+        sput!("crash during boot ", self.boot);
+        if !self.file.is_empty() {
+            sput!(" at ", self.file(), ":", self.line, ":", self.column);
+        }
+        sput!("\n  ", self.message(), "\n");
+        for line in self.log_lines() {
+            sput!("  | ", line, "\n");
+        }
+    }
+}
+
+#[link_section = ".persist"]
+static CRASH: Persistent<CrashRecord> = Persistent::new(CrashRecord {
+    line: 0,
+    column: 0,
+    boot: 0,
+    file: Text::new(),
+    message: Text::new(),
+    log: [Text::new(); LOG_LINES],
+});
+
+#[link_section = ".persist"]
+static BOOTS: Persistent<u16> = Persistent::new(0);
+
+/// Count a boot, call once at startup. Returns the new [`boot_count`].
+pub fn record_boot() -> u16 {
+    let boots = boot_count().wrapping_add(1);
+    BOOTS.store(boots);
+    boots
+}
+
+/// Number of times [`record_boot`] has been called since the count was first stored
+pub fn boot_count() -> u16 {
+    BOOTS.load().unwrap_or(0)
+}
+
+/// The record of the last crash, if there is one
+pub fn last_crash() -> Option<CrashRecord> {
+    CRASH.load()
+}
+
+pub fn clear() {
+    CRASH.clear();
+}
+
+/// Print the record of the last crash, if there is one. Returns whether there was.
+pub fn report() -> bool {
+    match last_crash() {
+        Some(record) => {
+            record.print();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Save a record of a panic, for applications that provide their own panic handler
+pub fn record_panic(info: &PanicInfo) {
+    let mut record = CrashRecord {
+        line: 0,
+        column: 0,
+        boot: boot_count(),
+        file: Text::new(),
+        message: Text::new(),
+        log: history(),
+    };
+    if let Some(location) = info.location() {
+        record.file.set_tail(location.file());
+        record.line = location.line();
+        record.column = location.column() as u16;
+    }
+    // Stops at the end of the buffer
+    fmt::Write::write_fmt(&mut record.message, format_args!("{}", info.message())).ok();
+    CRASH.store(record);
+}
+
+#[cfg(feature = "crash-record")]
+mod history {
+    use core::cell::RefCell;
+    use msp430::interrupt::{self, Mutex};
+    use super::{Text, LOG_LINES, LOG_LINE_LEN};
+
+    struct History {
+        lines: [Text<LOG_LINE_LEN>; LOG_LINES],
+        next: usize,
+    }
+
+    static HISTORY: Mutex<RefCell<History>> =
+        Mutex::new(RefCell::new(History { lines: [Text::new(); LOG_LINES], next: 0 }));
+
+    /// Keep a log line for the crash record, called by the logger
+    pub(crate) fn push(line: &Text<LOG_LINE_LEN>) {
+        interrupt::free(|cs| {
+            if let Ok(mut history) = HISTORY.borrow(cs).try_borrow_mut() {
+                let next = history.next;
+                history.lines[next] = *line;
+                history.next = (next + 1) % LOG_LINES;
+            }
+        });
+    }
+
+    /// Lines kept so far, oldest first
+    pub(super) fn lines() -> [Text<LOG_LINE_LEN>; LOG_LINES] {
+        let mut lines = [Text::new(); LOG_LINES];
+        interrupt::free(|cs| {
+            if let Ok(history) = HISTORY.borrow(cs).try_borrow() {
+                for (i, line) in lines.iter_mut().enumerate() {
+                    *line = history.lines[(history.next + i) % LOG_LINES];
+                }
+            }
+        });
+        lines
+    }
+}
+
+#[cfg(feature = "crash-record")]
+pub(crate) use history::push as log_line;
+
+#[cfg(feature = "crash-record")]
+fn history() -> [Text<LOG_LINE_LEN>; LOG_LINES] {
+    history::lines()
+}
+
+#[cfg(not(feature = "crash-record"))]
+fn history() -> [Text<LOG_LINE_LEN>; LOG_LINES] {
+    [Text::new(); LOG_LINES]
+}
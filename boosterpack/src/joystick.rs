@@ -0,0 +1,386 @@
+//! Driver for the BoosterPack's analog joystick and its select button
+//!
+//! The X axis is on ADC channel 5 (P1.5) and the Y axis on channel 8 (P5.0), both configured
+//! with `to_alternate3()`. The select button, on BoosterPack pin J1.5, pulls its pin low when
+//! pressed. Like the [accelerometer](crate::accelerometer), the ADC is only borrowed for each
+//! reading.
+//!
+//! Positions are normalized to -[`FULL_SCALE`]..=[`FULL_SCALE`], positive right and up, using a
+//! [`Calibration`] of the center and extents of the stick, which can be kept in FRAM:
+//! ```ignore
+//! let calibration = Calibration::load().unwrap_or(Calibration::for_resolution(10));
+//! let mut joystick = Joystick::new(x_pin, y_pin, select_pin, calibration);
+//! loop {
+//!     if let Ok(Some(event)) = joystick.poll(&mut adc) {
+//!         // move a menu cursor...
+//!     }
+//!     delay.delay_ms(20u16);
+//! }
+//! ```
+use core::marker::PhantomData;
+use embedded_hal::adc::{Channel, OneShot};
+use embedded_hal::digital::v2::InputPin;
+use crate::persist::{Persistent, Plain};
+use crate::queuebuf::QueueBuf;
+
+/// Magnitude of a fully deflected axis
+pub const FULL_SCALE: i16 = 1000;
+
+/// How far a direction has to drop below [`Config::threshold`] before it is released
+const HYSTERESIS: i16 = 100;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Error<A, P> {
+    /// Reading an axis failed
+    Adc(A),
+    /// Reading the select button failed
+    Pin(P),
+}
+
+/// Raw ADC readings of the stick's center and extents, for X then Y
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Calibration {
+    pub center: [u16; 2],
+    pub min: [u16; 2],
+    pub max: [u16; 2],
+}
+
+// Only u16 fields, so no padding
+unsafe impl Plain for Calibration {}
+
+#[link_section = ".persist"]
+static CALIBRATION: Persistent<Calibration> = Persistent::new(Calibration::for_resolution(10));
+
+impl Calibration {
+    /// Centered, using the whole range of an ADC with `bits` of resolution
+    pub const fn for_resolution(bits: u8) -> Self {
+        let max = (1u16 << bits) - 1;
+        Calibration {
+            center: [max / 2 + 1; 2],
+            min: [0; 2],
+            max: [max; 2],
+        }
+    }
+
+    /// The calibration saved with [`store`](Self::store), if there is one
+    pub fn load() -> Option<Self> {
+        CALIBRATION.load()
+    }
+
+    /// Save to FRAM, so it survives resets
+    pub fn store(&self) {
+        CALIBRATION.store(*self);
+    }
+
+    /// Forget the saved calibration
+    pub fn clear() {
+        CALIBRATION.clear();
+    }
+
+    // Reading to -FULL_SCALE..=FULL_SCALE
+    fn normalize(&self, axis: usize, raw: u16) -> i16 {
+        let center = self.center[axis] as i32;
+        let raw = raw as i32;
+        let value = if raw >= center {
+            (raw - center) * FULL_SCALE as i32 / (self.max[axis] as i32 - center).max(1)
+        } else {
+            -((center - raw) * FULL_SCALE as i32 / (center - self.min[axis] as i32).max(1))
+        };
+        value.clamp(-FULL_SCALE as i32, FULL_SCALE as i32) as i16
+    }
+}
+
+/// Normalized position, positive right and up
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Position {
+    pub x: i16,
+    pub y: i16,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Event {
+    /// Pushed in a direction, or still held there after the repeat delay
+    Move(Direction),
+    /// The select button went down
+    Press,
+    /// The select button came back up
+    Release,
+}
+
+impl Event {
+    // One byte each, to fit the byte queue
+    fn to_byte(self) -> u8 {
+        match self {
+            Event::Move(Direction::Up) => 0,
+            Event::Move(Direction::Down) => 1,
+            Event::Move(Direction::Left) => 2,
+            Event::Move(Direction::Right) => 3,
+            Event::Press => 4,
+            Event::Release => 5,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Self {
+        match byte {
+            0 => Event::Move(Direction::Up),
+            1 => Event::Move(Direction::Down),
+            2 => Event::Move(Direction::Left),
+            3 => Event::Move(Direction::Right),
+            4 => Event::Press,
+            _ => Event::Release,
+        }
+    }
+}
+
+/// Tuning of positions and events. Times count calls to [`Joystick::poll`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Config {
+    /// Positions closer to the center than this read as 0, with the rest of the range
+    /// stretched to still reach [`FULL_SCALE`]
+    pub dead_zone: i16,
+    /// Deflection needed for a [`Event::Move`]
+    pub threshold: i16,
+    /// Polls a direction is held before it repeats, 0 for no repeat
+    pub repeat_delay: u16,
+    /// Polls between repeats
+    pub repeat_interval: u16,
+}
+
+impl Default for Config {
+    /// Dead zone and threshold at 10% and 50% of full scale. Repeats after 25 polls, then every
+    /// 5, which is 500 ms and 100 ms when polling every 20 ms.
+    fn default() -> Self {
+        Config {
+            dead_zone: FULL_SCALE / 10,
+            threshold: FULL_SCALE / 2,
+            repeat_delay: 25,
+            repeat_interval: 5,
+        }
+    }
+}
+
+/// For reading the joystick through ADC pins `X` and `Y` and button pin `SEL`
+pub struct Joystick<ADC, X, Y, SEL> {
+    x: X,
+    y: Y,
+    select: SEL,
+    calibration: Calibration,
+    config: Config,
+    held: Option<Direction>,
+    held_polls: u16,
+    pressed: bool,
+    // events not handed out yet, when one poll produced more than one
+    pending: QueueBuf<4>,
+    _adc: PhantomData<ADC>,
+}
+
+impl<ADC, X, Y, SEL> Joystick<ADC, X, Y, SEL>
+where
+    X: Channel<ADC>,
+    Y: Channel<ADC>,
+    SEL: InputPin,
+{
+    pub fn new(x: X, y: Y, select: SEL, calibration: Calibration) -> Self {
+        Joystick {
+            x,
+            y,
+            select,
+            calibration,
+            config: Config::default(),
+            held: None,
+            held_polls: 0,
+            pressed: false,
+            pending: QueueBuf::new([0; 4]),
+            _adc: PhantomData,
+        }
+    }
+
+    pub fn config(&self) -> Config {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+    }
+
+    pub fn calibration(&self) -> Calibration {
+        self.calibration
+    }
+
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.calibration = calibration;
+    }
+
+    /// Blocking read of both ADC channels, X first
+    pub fn read_raw<A, E>(&mut self, adc: &mut A) -> Result<[u16; 2], E>
+    where
+        A: OneShot<ADC, u16, X, Error = E> + OneShot<ADC, u16, Y, Error = E>,
+    {
+        let x = nb::block!(adc.read(&mut self.x))?;
+        let y = nb::block!(adc.read(&mut self.y))?;
+        Ok([x, y])
+    }
+
+    /// Blocking read of the normalized position, with the dead zone applied
+    pub fn position<A, E>(&mut self, adc: &mut A) -> Result<Position, E>
+    where
+        A: OneShot<ADC, u16, X, Error = E> + OneShot<ADC, u16, Y, Error = E>,
+    {
+        let [x, y] = self.read_raw(adc)?;
+        Ok(Position {
+            x: self.apply_dead_zone(self.calibration.normalize(0, x)),
+            // the Y reading increases downwards
+            y: self.apply_dead_zone(-self.calibration.normalize(1, y)),
+        })
+    }
+
+    fn apply_dead_zone(&self, value: i16) -> i16 {
+        let dead_zone = self.config.dead_zone.clamp(0, FULL_SCALE - 1) as i32;
+        let magnitude = value.unsigned_abs() as i32;
+        if magnitude <= dead_zone {
+            return 0;
+        }
+        let scaled = ((magnitude - dead_zone) * FULL_SCALE as i32 / (FULL_SCALE as i32 - dead_zone)) as i16;
+        if value < 0 { -scaled } else { scaled }
+    }
+
+    pub fn is_pressed(&self) -> Result<bool, SEL::Error> {
+        self.select.is_low()
+    }
+
+    /// Read the stick and button, returning at most one event. A poll that sees both a button
+    /// change and a move returns the button event and keeps the move for the next poll. Call at
+    /// a steady rate, which sets the timing of repeats.
+    pub fn poll<A, E>(&mut self, adc: &mut A) -> Result<Option<Event>, Error<E, SEL::Error>>
+    where
+        A: OneShot<ADC, u16, X, Error = E> + OneShot<ADC, u16, Y, Error = E>,
+    {
+        let position = self.position(adc).map_err(Error::Adc)?;
+        let pressed = self.is_pressed().map_err(Error::Pin)?;
+
+        if pressed != self.pressed {
+            self.pressed = pressed;
+            self.queue(if pressed { Event::Press } else { Event::Release });
+        }
+        if let Some(movement) = self.update_direction(position) {
+            self.queue(movement);
+        }
+
+        if self.pending.has_data() {
+            Ok(Some(Event::from_byte(self.pending.get())))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // Two events in one poll are rare and at most one is handed out per poll, so the queue only
+    // fills if that keeps happening; the newest events are dropped then
+    fn queue(&mut self, event: Event) {
+        if !self.pending.is_full() {
+            self.pending.put(event.to_byte());
+        }
+    }
+
+    fn update_direction(&mut self, position: Position) -> Option<Event> {
+        let threshold = self.config.threshold;
+        let still_held = self.held.is_some_and(|held| {
+            deflection(position, held) >= threshold.saturating_sub(HYSTERESIS)
+        });
+        if still_held {
+            self.held_polls = self.held_polls.saturating_add(1);
+            let Config { repeat_delay, repeat_interval, .. } = self.config;
+            if repeat_delay == 0 || self.held_polls < repeat_delay {
+                return None;
+            }
+            let since_delay = self.held_polls - repeat_delay;
+            if since_delay.is_multiple_of(repeat_interval.max(1)) {
+                return self.held.map(Event::Move);
+            }
+            return None;
+        }
+
+        // the axis pushed furthest picks the direction
+        let direction = if position.x.unsigned_abs() >= position.y.unsigned_abs() {
+            if position.x > 0 { Direction::Right } else { Direction::Left }
+        } else if position.y > 0 {
+            Direction::Up
+        } else {
+            Direction::Down
+        };
+        self.held_polls = 0;
+        if deflection(position, direction) >= threshold {
+            self.held = Some(direction);
+            Some(Event::Move(direction))
+        } else {
+            self.held = None;
+            None
+        }
+    }
+
+    /// Set the center from `samples` readings taken while the stick is left alone
+    pub fn calibrate_center<A, E>(&mut self, adc: &mut A, samples: u8) -> Result<Calibration, E>
+    where
+        A: OneShot<ADC, u16, X, Error = E> + OneShot<ADC, u16, Y, Error = E>,
+    {
+        let samples = samples.max(1);
+        let mut sum = [0u32; 2];
+        for _ in 0..samples {
+            let raw = self.read_raw(adc)?;
+            for (total, reading) in sum.iter_mut().zip(raw) {
+                *total += reading as u32;
+            }
+        }
+        self.calibration.center = sum.map(|total| (total / samples as u32) as u16);
+        Ok(self.calibration)
+    }
+
+    /// Shrink the extents to the center, before calling
+    /// [`calibrate_extents`](Self::calibrate_extents)
+    pub fn reset_extents(&mut self) {
+        self.calibration.min = self.calibration.center;
+        self.calibration.max = self.calibration.center;
+    }
+
+    /// Widen the extents to include the current reading. Call repeatedly while the stick is
+    /// moved around its whole range.
+    pub fn calibrate_extents<A, E>(&mut self, adc: &mut A) -> Result<Calibration, E>
+    where
+        A: OneShot<ADC, u16, X, Error = E> + OneShot<ADC, u16, Y, Error = E>,
+    {
+        let raw = self.read_raw(adc)?;
+        for (axis, reading) in raw.into_iter().enumerate() {
+            self.calibration.min[axis] = self.calibration.min[axis].min(reading);
+            self.calibration.max[axis] = self.calibration.max[axis].max(reading);
+        }
+        Ok(self.calibration)
+    }
+
+    /// Save the current calibration to FRAM, see [`Calibration::store`]
+    pub fn save_calibration(&self) {
+        self.calibration.store();
+    }
+
+    /// Give back the pins
+    pub fn release(self) -> (X, Y, SEL) {
+        (self.x, self.y, self.select)
+    }
+}
+
+// How far the stick is pushed in a direction, negative if the other way
+fn deflection(position: Position, direction: Direction) -> i16 {
+    match direction {
+        Direction::Up => position.y,
+        Direction::Down => -position.y,
+        Direction::Right => position.x,
+        Direction::Left => -position.x,
+    }
+}
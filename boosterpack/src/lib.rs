@@ -3,10 +3,23 @@
 #![feature(abi_msp430_interrupt)]
 #![feature(core_panic)]
 
+pub mod accelerometer;
+pub mod buttons;
+pub mod buzzer;
+pub mod crash;
+pub mod log;
+pub mod joystick;
+pub mod light_filter;
 pub mod opt3001;
 pub mod serial_utils;
 pub mod stream;
+pub mod supervisor;
+pub mod tmp006;
+pub mod persist;
 pub mod queuebuf;
+pub mod reset;
+pub mod shell;
+mod panic;
 
 pub use msp430fr2355 as pac;
 pub use embedded_hal as hal;
@@ -0,0 +1,259 @@
+//! Smoothing for OPT3001 readings
+//!
+//! [`LuxFilter`] keeps the last `N` samples and combines them as picked by a [`Filter`], which can
+//! be changed at runtime. [`FilteredOpt3001`] wraps a [`DeviceOpt3001`] in continuous mode and
+//! feeds each new conversion through the filter:
+//! ```ignore
+//! let mut light: FilteredOpt3001<_, 8> = FilteredOpt3001::new(device, Filter::Median(5))?;
+//! loop {
+//!     if let Ok(lux) = light.poll() {
+//!         sputln!("lux: ", lux.whole);
+//!     }
+//!     // other work
+//! }
+//! ```
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use crate::opt3001::{CentiluxMean, DeviceOpt3001, Error, Lux, Mode};
+
+/// How samples are combined
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Filter {
+    /// Pass the latest sample through
+    None,
+    /// Mean of the last `n` samples
+    MovingAverage(u8),
+    /// Each sample moves the output 1/2^`shift` of the way towards it. Saturates at about
+    /// 167000 lux, twice what the sensor can measure.
+    Exponential(u8),
+    /// Median of the last `n` samples, which ignores short flicker entirely
+    Median(u8),
+}
+
+/// Filters a stream of [`Lux`] samples, remembering the last `N`.
+/// Window sizes above `N` are treated as `N`.
+pub struct LuxFilter<const N: usize> {
+    filter: Filter,
+    // centilux, oldest overwritten first
+    samples: [u32; N],
+    next: usize,
+    len: usize,
+    // centilux << EMA_FRAC_BITS, saturating above 2^24 centilux, twice the sensor's range
+    ema: u32,
+    output: Option<Lux>,
+}
+
+const EMA_FRAC_BITS: u32 = 8;
+
+impl<const N: usize> LuxFilter<N> {
+    pub const fn new(filter: Filter) -> Self {
+        LuxFilter {
+            filter,
+            samples: [0; N],
+            next: 0,
+            len: 0,
+            ema: 0,
+            output: None,
+        }
+    }
+
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+
+    /// Change the filter. Kept samples are reused, but exponential smoothing starts over.
+    pub fn set_filter(&mut self, filter: Filter) {
+        if let Filter::Exponential(_) = filter {
+            if let Some(lux) = self.latest() {
+                self.ema = lux.centilux().saturating_mul(1 << EMA_FRAC_BITS);
+            }
+        }
+        self.filter = filter;
+        self.output = self.compute();
+    }
+
+    /// Forget all samples
+    pub fn reset(&mut self) {
+        self.next = 0;
+        self.len = 0;
+        self.output = None;
+    }
+
+    /// Add a sample and return the new output
+    pub const fn push(&mut self, lux: Lux) -> Lux {
+        let centilux = lux.centilux();
+        if N > 0 {
+            self.samples[self.next] = centilux;
+            self.next = (self.next + 1) % N;
+            if self.len < N {
+                self.len += 1;
+            }
+        }
+        if let Filter::Exponential(shift) = self.filter {
+            let target = centilux.saturating_mul(1 << EMA_FRAC_BITS);
+            let shift = if shift > 31 { 31 } else { shift as u32 };
+            self.ema = if self.output.is_none() {
+                target
+            } else if target >= self.ema {
+                self.ema + ((target - self.ema) >> shift)
+            } else {
+                self.ema - ((self.ema - target) >> shift)
+            };
+        }
+        let output = match self.compute() {
+            Some(output) => output,
+            None => lux,
+        };
+        self.output = Some(output);
+        output
+    }
+
+    /// The output after the last sample, `None` before the first
+    pub const fn value(&self) -> Option<Lux> {
+        self.output
+    }
+
+    /// The last sample given to [`push`](Self::push)
+    pub fn latest(&self) -> Option<Lux> {
+        if self.len == 0 {
+            return None;
+        }
+        Some(Lux::from_centilux(self.samples[(self.next + N - 1) % N]))
+    }
+
+    /// Number of samples kept, up to `N`
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // How many of the kept samples a window of `n` covers
+    const fn window(&self, n: u8) -> usize {
+        let n = if n == 0 { 1 } else { n as usize };
+        if n < self.len { n } else { self.len }
+    }
+
+    // The `i`th most recent sample, 0 being the newest
+    const fn recent(&self, i: usize) -> u32 {
+        self.samples[(self.next + N - 1 - i) % N]
+    }
+
+    const fn compute(&self) -> Option<Lux> {
+        if self.len == 0 {
+            return None;
+        }
+        let centilux = match self.filter {
+            Filter::None => self.recent(0),
+            Filter::MovingAverage(n) => {
+                let count = self.window(n);
+                let mut mean = CentiluxMean::new(count as u32);
+                let mut i = 0;
+                while i < count {
+                    mean.add(self.recent(i));
+                    i += 1;
+                }
+                mean.value()
+            }
+            Filter::Exponential(_) => {
+                self.ema.saturating_add(1 << (EMA_FRAC_BITS - 1)) >> EMA_FRAC_BITS
+            }
+            Filter::Median(n) => {
+                let count = self.window(n);
+                let mut sorted = [0u32; N];
+                let mut added = 0;
+                while added < count {
+                    // insertion sort, the window is small
+                    let sample = self.recent(added);
+                    let mut i = added;
+                    while i > 0 && sorted[i - 1] > sample {
+                        sorted[i] = sorted[i - 1];
+                        i -= 1;
+                    }
+                    sorted[i] = sample;
+                    added += 1;
+                }
+                if count % 2 == 1 {
+                    sorted[count / 2]
+                } else {
+                    // mean of the middle two, halved first so it can't overflow
+                    let (a, b) = (sorted[count / 2 - 1], sorted[count / 2]);
+                    a / 2 + b / 2 + (a % 2 + b % 2) / 2
+                }
+            }
+        };
+        Some(Lux::from_centilux(centilux))
+    }
+}
+
+// Pushes `samples` and returns the last output in centilux
+const fn run<const N: usize>(filter: Filter, samples: &[u32]) -> u32 {
+    let mut lux_filter = LuxFilter::<N>::new(filter);
+    let mut output = 0;
+    let mut i = 0;
+    while i < samples.len() {
+        output = lux_filter.push(Lux::from_centilux(samples[i])).centilux();
+        i += 1;
+    }
+    output
+}
+
+// Sliding window, halving steps and an even-sized median
+const _: () = {
+    assert!(run::<4>(Filter::MovingAverage(3), &[100, 200, 600, 1000]) == 600);
+    assert!(run::<4>(Filter::Exponential(1), &[1000, 0, 0]) == 250);
+    assert!(run::<4>(Filter::Median(4), &[500, 100, 900, 300]) == 400);
+};
+
+/// An OPT3001 in continuous mode with its results filtered
+pub struct FilteredOpt3001<I2C, const N: usize> {
+    device: DeviceOpt3001<I2C>,
+    filter: LuxFilter<N>,
+}
+
+impl<I2C, E, const N: usize> FilteredOpt3001<I2C, N>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+{
+    /// Switches the sensor to continuous mode, keeping the rest of its configuration
+    pub fn new(mut device: DeviceOpt3001<I2C>, filter: Filter) -> Result<Self, Error<E>> {
+        device.set_mode(Mode::Continuous)?;
+        Ok(FilteredOpt3001 {
+            device,
+            filter: LuxFilter::new(filter),
+        })
+    }
+
+    /// Feed a finished conversion through the filter and return the new output.
+    /// A conversion finishes every 100 or 800 ms depending on the conversion time.
+    pub fn poll(&mut self) -> nb::Result<Lux, Error<E>> {
+        let lux = self.device.poll_measurement()?;
+        Ok(self.filter.push(lux))
+    }
+
+    /// The output after the last conversion, `None` before the first
+    pub fn value(&self) -> Option<Lux> {
+        self.filter.value()
+    }
+
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter.set_filter(filter);
+    }
+
+    pub fn filter(&self) -> &LuxFilter<N> {
+        &self.filter
+    }
+
+    /// Access the sensor, e.g. to change its conversion time or limits.
+    /// Reading the configuration can clear a pending result, so the next one is only seen
+    /// by [`poll`](Self::poll) a conversion later.
+    pub fn device(&mut self) -> &mut DeviceOpt3001<I2C> {
+        &mut self.device
+    }
+
+    /// Stop filtering, leaving the sensor in continuous mode
+    pub fn release(self) -> DeviceOpt3001<I2C> {
+        self.device
+    }
+}
@@ -0,0 +1,377 @@
+//! Leveled logging to the serial console.
+//!
+//! Use the [`error!`](crate::error), [`warn!`](crate::warn), [`info!`](crate::info),
+//! [`debug!`](crate::debug) and [`trace!`](crate::trace) macros, which take `core::fmt` style
+//! arguments and optionally a tag naming the module the message came from:
+//! ```ignore
+//! info!("configuring ADC");
+//! warn!(tag: "opt3001", "read failed: {}", attempts);
+//! ```
+//!
+//! The most verbose level that is compiled in is picked with one of the `log-level-off`,
+//! `log-level-error`, `log-level-warn`, `log-level-info`, `log-level-debug` or `log-level-trace`
+//! cargo features, defaulting to info. If several are enabled the least verbose one wins.
+//! Messages above that level are removed at compile time, along with their format strings.
+//!
+//! With the `log-timestamps` feature each line starts with the current [`ticks`] count.
+//! The application is responsible for calling [`tick`], e.g. from a timer interrupt.
+//!
+//! # Binary logging
+//! The `log-binary` feature replaces the formatted text with compact frames, to save the flash
+//! and cycles spent on `core::fmt`. Each log call site places its format string, tag and location
+//! as a NUL terminated entry in the `.blog` section, which is kept in the ELF file but not loaded
+//! onto the device (see `memory.x`). The address of that entry is sent as the message id, followed
+//! by the raw arguments, and `tools/blog_decode.py` turns the stream back into text using the ELF
+//! file.
+//!
+//! In this mode the format string and tag have to be literals, and arguments have to implement
+//! [`BinArg`]. Formatting options such as `{:x}` or `{:>8}` are applied by the decoder.
+//!
+//! A frame is laid out as:
+//! ```text
+//! FRAME_START | level | id (u16) | [ticks (u32) with log-timestamps] | arg count | args... | crc
+//! ```
+//! All values are little endian, and each argument starts with one of the `TAG_*` bytes. The
+//! last byte is a CRC-8 (polynomial 0x07) of everything before it. A frame is built on the stack
+//! and queued in one go, so frames logged from interrupt handlers don't break into it. Frames are
+//! limited to [`FRAME_LEN`] bytes: strings are cut short to fit, and a frame whose other
+//! arguments don't fit is dropped. Frames can be mixed with plain
+//! text: the start bytes are not ASCII, and the decoder only takes one as the start of a frame if
+//! the frame after it is complete and its CRC matches, so UTF-8 text containing them passes
+//! through.
+//!
+//! With the `crash-record` feature the last few text lines are kept for the
+//! [crash record](crate::crash).
+//!
+//! Output goes to the default console and is discarded until one is set up, see
+//! [`init_serial`](crate::serial_utils::init_serial).
+
+use core::fmt;
+use portable_atomic::{AtomicU32, Ordering::Relaxed};
+use crate::crash::{Text, LOG_LINE_LEN};
+use crate::serial_utils::{fmt_u32, print_bytes, Pad};
+#[cfg(feature = "log-binary")]
+use crate::serial_utils::print_bytes_atomic;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Level {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+/// Most verbose level compiled in, 0 when logging is off
+const MAX_LEVEL: u8 = if cfg!(feature = "log-level-off") {
+    0
+} else if cfg!(feature = "log-level-error") {
+    Level::Error as u8
+} else if cfg!(feature = "log-level-warn") {
+    Level::Warn as u8
+} else if cfg!(feature = "log-level-info") {
+    Level::Info as u8
+} else if cfg!(feature = "log-level-debug") {
+    Level::Debug as u8
+} else if cfg!(feature = "log-level-trace") {
+    Level::Trace as u8
+} else {
+    Level::Info as u8
+};
+
+impl Level {
+    /// Whether messages at this level are compiled in
+    #[inline(always)]
+    pub const fn enabled(self) -> bool {
+        (self as u8) <= MAX_LEVEL
+    }
+
+    // Padded to the same width
+    fn label(self) -> &'static str {
+        match self {
+            Level::Error => "ERROR ",
+            Level::Warn => "WARN  ",
+            Level::Info => "INFO  ",
+            Level::Debug => "DEBUG ",
+            Level::Trace => "TRACE ",
+        }
+    }
+}
+
+static TICKS: AtomicU32 = AtomicU32::new(0);
+
+/// Advance the log timestamp by one
+#[inline]
+pub fn tick() {
+    TICKS.add(1, Relaxed);
+}
+
+/// Current log timestamp
+#[inline]
+pub fn ticks() -> u32 {
+    TICKS.load(Relaxed)
+}
+
+// Prints to the serial console, keeping a copy of the start of the line for the crash record
+struct Tee {
+    line: Text<LOG_LINE_LEN>,
+}
+
+impl fmt::Write for Tee {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        print_bytes(s.as_bytes());
+        if cfg!(feature = "crash-record") {
+            self.line.push_str(s);
+        }
+        Ok(())
+    }
+}
+
+#[doc(hidden)]
+pub fn __write(level: Level, tag: Option<&str>, args: fmt::Arguments) {
+    if cfg!(feature = "log-timestamps") {
+        print_bytes(b"[");
+        print_bytes(fmt_u32(ticks(), Pad::Spaces(8)).as_bytes());
+        print_bytes(b"] ");
+    }
+    let mut tee = Tee { line: Text::new() };
+    fmt::Write::write_str(&mut tee, level.label()).ok();
+    if let Some(tag) = tag {
+        fmt::Write::write_str(&mut tee, "[").ok();
+        fmt::Write::write_str(&mut tee, tag).ok();
+        fmt::Write::write_str(&mut tee, "] ").ok();
+    }
+    fmt::Write::write_fmt(&mut tee, args).ok();
+    print_bytes(b"\n");
+    #[cfg(feature = "crash-record")]
+    crate::crash::log_line(&tee.line);
+}
+
+/// Starts a binary frame without timestamp
+pub const FRAME_START: u8 = 0xB1;
+/// Starts a binary frame with timestamp
+pub const FRAME_START_TIMESTAMP: u8 = 0xB2;
+
+// Argument type tags for binary frames
+pub const TAG_U8: u8 = 0x01;
+pub const TAG_U16: u8 = 0x02;
+pub const TAG_U32: u8 = 0x03;
+pub const TAG_I8: u8 = 0x11;
+pub const TAG_I16: u8 = 0x12;
+pub const TAG_I32: u8 = 0x13;
+pub const TAG_BOOL: u8 = 0x20;
+pub const TAG_CHAR: u8 = 0x21;
+/// Followed by a length byte, strings are cut off at 255 bytes
+pub const TAG_STR: u8 = 0x30;
+
+/// Largest binary frame, including the CRC
+pub const FRAME_LEN: usize = 64;
+
+/// A binary frame being built, sent by [`end`](Frame::end)
+#[cfg(feature = "log-binary")]
+pub struct Frame {
+    bytes: [u8; FRAME_LEN],
+    len: usize,
+    crc: u8,
+    overflow: bool,
+}
+
+#[cfg(feature = "log-binary")]
+impl Frame {
+    #[doc(hidden)]
+    pub fn start(level: Level, id: u16, num_args: u8) -> Self {
+        let mut frame = Frame { bytes: [0; FRAME_LEN], len: 0, crc: 0, overflow: false };
+        if cfg!(feature = "log-timestamps") {
+            frame.push(&[FRAME_START_TIMESTAMP, level as u8]);
+            frame.push(&id.to_le_bytes());
+            frame.push(&ticks().to_le_bytes());
+        } else {
+            frame.push(&[FRAME_START, level as u8]);
+            frame.push(&id.to_le_bytes());
+        }
+        frame.push(&[num_args]);
+        frame
+    }
+
+    /// Room left for arguments, keeping a byte for the CRC
+    pub fn room(&self) -> usize {
+        FRAME_LEN - 1 - self.len
+    }
+
+    /// Add part of an argument, updating the CRC. If it doesn't fit the frame won't be sent.
+    pub fn push(&mut self, bytes: &[u8]) {
+        if bytes.len() > self.room() {
+            self.overflow = true;
+            return;
+        }
+        for &byte in bytes {
+            self.bytes[self.len] = byte;
+            self.len += 1;
+            self.crc ^= byte;
+            for _ in 0..8 {
+                self.crc = if self.crc & 0x80 != 0 { (self.crc << 1) ^ 0x07 } else { self.crc << 1 };
+            }
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn end(mut self) {
+        if self.overflow {
+            return;
+        }
+        self.bytes[self.len] = self.crc;
+        print_bytes_atomic(&self.bytes[..=self.len]);
+    }
+}
+
+/// Values that can be sent as arguments of a binary log frame
+#[cfg(feature = "log-binary")]
+pub trait BinArg {
+    /// Add the tag byte and value to the frame with [`Frame::push`]
+    fn emit(&self, frame: &mut Frame);
+}
+
+#[cfg(feature = "log-binary")]
+macro_rules! impl_bin_arg {
+    ($($ty:ty => $tag:expr),*) => {
+        $(impl BinArg for $ty {
+            #[inline]
+            fn emit(&self, frame: &mut Frame) {
+                frame.push(&[$tag]);
+                frame.push(&self.to_le_bytes());
+            }
+        })*
+    };
+}
+
+#[cfg(feature = "log-binary")]
+impl_bin_arg!(u8 => TAG_U8, u16 => TAG_U16, u32 => TAG_U32, i8 => TAG_I8, i16 => TAG_I16, i32 => TAG_I32);
+
+#[cfg(feature = "log-binary")]
+impl BinArg for bool {
+    fn emit(&self, frame: &mut Frame) {
+        frame.push(&[TAG_BOOL, *self as u8]);
+    }
+}
+
+#[cfg(feature = "log-binary")]
+impl BinArg for char {
+    fn emit(&self, frame: &mut Frame) {
+        frame.push(&[TAG_CHAR]);
+        frame.push(&(*self as u32).to_le_bytes());
+    }
+}
+
+#[cfg(feature = "log-binary")]
+impl BinArg for str {
+    fn emit(&self, frame: &mut Frame) {
+        let bytes = &self.as_bytes()[..self.len().min(255).min(frame.room().saturating_sub(2))];
+        frame.push(&[TAG_STR, bytes.len() as u8]);
+        frame.push(bytes);
+    }
+}
+
+#[cfg(feature = "log-binary")]
+impl<T: BinArg + ?Sized> BinArg for &T {
+    #[inline]
+    fn emit(&self, frame: &mut Frame) {
+        (**self).emit(frame);
+    }
+}
+
+/// Copy a `.blog` entry into an array of its length
+#[cfg(feature = "log-binary")]
+#[doc(hidden)]
+pub const fn __entry<const N: usize>(text: &str) -> [u8; N] {
+    let bytes = text.as_bytes();
+    let mut entry = [0; N];
+    let mut i = 0;
+    while i < N {
+        entry[i] = bytes[i];
+        i += 1;
+    }
+    entry
+}
+
+#[cfg(feature = "log-binary")]
+#[doc(hidden)]
+pub const fn __one(_arg: &str) -> u8 {
+    1
+}
+
+/// Log a message at the given [`Level`](crate::log::Level)
+#[cfg(not(feature = "log-binary"))]
+#[macro_export]
+macro_rules! log {
+    ($level:expr, tag: $tag:expr, $($arg:tt)+) => {{
+        let level: $crate::log::Level = $level;
+        if level.enabled() {
+            $crate::log::__write(level, ::core::option::Option::Some($tag), ::core::format_args!($($arg)+));
+        }
+    }};
+    ($level:expr, $($arg:tt)+) => {{
+        let level: $crate::log::Level = $level;
+        if level.enabled() {
+            $crate::log::__write(level, ::core::option::Option::None, ::core::format_args!($($arg)+));
+        }
+    }};
+}
+
+/// Log a message at the given [`Level`](crate::log::Level)
+#[cfg(feature = "log-binary")]
+#[macro_export]
+macro_rules! log {
+    ($level:expr, tag: $tag:literal, $fmt:literal $(, $arg:expr)* $(,)?) => {{
+        let level: $crate::log::Level = $level;
+        if level.enabled() {
+            // The entry carries everything the decoder needs, its address is the id. Being
+            // in the contents rather than the symbol name, identical entries can't clash.
+            const TEXT: &str = ::core::concat!(
+                "blog\x1f", $tag, "\x1f", ::core::file!(), ":", ::core::line!(), ":", ::core::column!(), "\x1f", $fmt, "\0"
+            );
+            #[link_section = ".blog"]
+            #[used]
+            static ENTRY: [u8; TEXT.len()] = $crate::log::__entry(TEXT);
+            let id = ENTRY.as_ptr() as usize as u16;
+            // not mutated when there are no arguments
+            #[allow(unused_mut)]
+            let mut frame = $crate::log::Frame::start(level, id, 0u8 $(+ $crate::log::__one(::core::stringify!($arg)))*);
+            $( $crate::log::BinArg::emit(&$arg, &mut frame); )*
+            frame.end();
+        }
+    }};
+    ($level:expr, $fmt:literal $(, $arg:expr)* $(,)?) => {
+        $crate::log!($level, tag: "", $fmt $(, $arg)*)
+    };
+}
+
+/// Log a message at the error level
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => { $crate::log!($crate::log::Level::Error, $($arg)+) };
+}
+
+/// Log a message at the warn level
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => { $crate::log!($crate::log::Level::Warn, $($arg)+) };
+}
+
+/// Log a message at the info level
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => { $crate::log!($crate::log::Level::Info, $($arg)+) };
+}
+
+/// Log a message at the debug level
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => { $crate::log!($crate::log::Level::Debug, $($arg)+) };
+}
+
+/// Log a message at the trace level
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)+) => { $crate::log!($crate::log::Level::Trace, $($arg)+) };
+}
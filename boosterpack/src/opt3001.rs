@@ -1,18 +1,268 @@
 //! Driver for the OPT3001 Ambient Light Sensor
-use embedded_hal::prelude::{_embedded_hal_blocking_i2c_Read, _embedded_hal_blocking_i2c_Write};
-use msp430fr2x5x_hal::i2c::{EUsciI2CBus, I2CErr, SDL};
+//!
+//! The driver works with any bus implementing the embedded-hal 0.2 blocking I2C `Write` and
+//! `WriteRead` traits, such as the HAL's `SDL`. With the `embedded-hal-1` feature, buses
+//! implementing the embedded-hal 1.0 `I2c` trait can be used through [`Eh1`].
+//!
+//! # Threshold interrupts
+//! The open-drain INT output goes active when the result leaves the window set with
+//! [`DeviceOpt3001::set_limits`] (for [`FaultCount`] conversions in a row), or after every
+//! conversion in [end-of-conversion mode](DeviceOpt3001::set_end_of_conversion_mode).
+//! The driver doesn't touch the pin it is wired to; the application has to set it up:
+//! - as an input with a pull-up, since INT is open drain
+//! - interrupting on the falling edge for [`Polarity::ActiveLow`] (PxIES bit set), or the rising
+//!   edge for [`Polarity::ActiveHigh`]
+//! - with its PxIFG bit cleared after choosing the edge, as changing PxIES can set it, and only
+//!   then PxIE set
+//!
+//! The port's interrupt handler then has to clear the pin's PxIFG bit, or reading PxIV does it,
+//! otherwise the handler runs again as soon as it returns. It calls [`on_int_interrupt`] with the
+//! sensor's [`Address`], after which the application can sleep until [`take_int`] returns
+//! `true` for that address, and read
+//! [`DeviceOpt3001::status`] to see which limit was crossed, which also releases a
+//! [latched](Latch::Latched) INT.
+//! `examples/opt3001_int.rs` does this for the BoosterPack, where INT is on P3.4:
+//! ```ignore
+//! const INT_PIN: u8 = 1 << 4;
+//! let p3 = unsafe{&*pac::P3::ptr()};
+//! p3.p3ies.modify(|r, w| unsafe{w.bits(r.bits() | INT_PIN)});
+//! p3.p3ifg.modify(|r, w| unsafe{w.bits(r.bits() & !INT_PIN)});
+//! p3.p3ie.modify(|r, w| unsafe{w.bits(r.bits() | INT_PIN)});
+//!
+//! #[interrupt]
+//! fn PORT3(_cs: CriticalSection) {
+//!     let p3 = unsafe{&*pac::P3::ptr()};
+//!     if p3.p3iv.read().bits() != 0 {
+//!         opt3001::on_int_interrupt(Address::Gnd);
+//!     }
+//! }
+//! ```
+use core::fmt;
+use core::ops::{Add, AddAssign};
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use portable_atomic::{AtomicBool, Ordering::Relaxed};
 
+/// I2C address, picked by what the ADDR pin is connected to
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Address {
+    /// 0x44, used on the BoosterPack
+    Gnd = 0x44,
+    Vdd = 0x45,
+    Sda = 0x46,
+    Scl = 0x47,
+}
+
+// Register addresses, for DeviceOpt3001::read_register and write_register
+pub const REG_RESULT: u8 = 0x00;
+pub const REG_CONFIG: u8 = 0x01;
+pub const REG_LOW_LIMIT: u8 = 0x02;
+pub const REG_HIGH_LIMIT: u8 = 0x03;
+pub const REG_MANUFACTURER_ID: u8 = 0x7E;
+pub const REG_DEVICE_ID: u8 = 0x7F;
+
+/// "TI" in ASCII
+pub const MANUFACTURER_ID: u16 = 0x5449;
+pub const DEVICE_ID: u16 = 0x3001;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Error<E> {
+    /// The bus transfer failed
+    I2c(E),
+    /// Something answered, but with this manufacturer ID instead of [`MANUFACTURER_ID`]
+    WrongManufacturer(u16),
+    /// Something answered, but with this device ID instead of [`DEVICE_ID`]
+    WrongDevice(u16),
+    /// A conversion didn't finish in time
+    Timeout,
+}
+
+/// Full-scale range, each one doubling the previous
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Range {
+    /// The sensor picks the range for each conversion
+    Auto,
+    /// Range 0 (40.95 lux full scale) to 11 (83865.6 lux). Values above 11 are treated as 11.
+    Fixed(u8),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConversionTime {
+    Ms100,
+    Ms800,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    Shutdown,
+    /// Convert once, then go back to shutdown
+    SingleShot,
+    Continuous,
+}
+
+/// How the INT pin and flags behave when a limit is crossed
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Latch {
+    /// Follow the result, with the limits acting as hysteresis
+    Transparent,
+    /// Stay set until the configuration register is read
+    Latched,
+}
+
+/// Level of the INT pin when active
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Polarity {
+    ActiveLow,
+    ActiveHigh,
+}
+
+/// Consecutive results past a limit needed to set the fault flags
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FaultCount {
+    One,
+    Two,
+    Four,
+    Eight,
+}
+
+/// Writable fields of the configuration register (0x01)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Config {
+    pub range: Range,
+    pub conversion_time: ConversionTime,
+    pub mode: Mode,
+    pub latch: Latch,
+    pub polarity: Polarity,
+    /// Compare only the mantissa with the limits, for use with a fixed range
+    pub mask_exponent: bool,
+    pub fault_count: FaultCount,
+}
+
+/// Read-only flags of the configuration register
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Status {
+    /// The result was too bright for the range
+    pub overflow: bool,
+    /// A conversion finished since the register was last read
+    pub conversion_ready: bool,
+    /// The result was above the high limit
+    pub flag_high: bool,
+    /// The result was below the low limit
+    pub flag_low: bool,
+}
 
-static OPT3001_ADDRESS:u8 = 0x44;
+// Configuration register fields
+const CFG_RN_SHIFT: u16 = 12;
+const CFG_RN_AUTO: u16 = 0b1100;
+const CFG_CT: u16 = 1 << 11;
+const CFG_M_SHIFT: u16 = 9;
+const CFG_OVF: u16 = 1 << 8;
+const CFG_CRF: u16 = 1 << 7;
+const CFG_FH: u16 = 1 << 6;
+const CFG_FL: u16 = 1 << 5;
+const CFG_L: u16 = 1 << 4;
+const CFG_POL: u16 = 1 << 3;
+const CFG_ME: u16 = 1 << 2;
+const CFG_FC_MASK: u16 = 0b11;
+
+impl Default for Config {
+    /// Automatic range, 100 ms conversions, continuous, latched, active low INT,
+    /// one fault. The configuration set by [`DeviceOpt3001::new`].
+    fn default() -> Self {
+        Config {
+            range: Range::Auto,
+            conversion_time: ConversionTime::Ms100,
+            mode: Mode::Continuous,
+            latch: Latch::Latched,
+            polarity: Polarity::ActiveLow,
+            mask_exponent: false,
+            fault_count: FaultCount::One,
+        }
+    }
+}
+
+impl Status {
+    /// Decode the flags of a configuration register value
+    pub fn from_bits(bits: u16) -> Self {
+        Status {
+            overflow: bits & CFG_OVF != 0,
+            conversion_ready: bits & CFG_CRF != 0,
+            flag_high: bits & CFG_FH != 0,
+            flag_low: bits & CFG_FL != 0,
+        }
+    }
+}
+
+impl Config {
+    /// Decode the configuration register, ignoring the read-only flags
+    pub fn from_bits(bits: u16) -> Self {
+        Config {
+            range: match bits >> CFG_RN_SHIFT {
+                rn @ 0..=11 => Range::Fixed(rn as u8),
+                _ => Range::Auto,
+            },
+            conversion_time: if bits & CFG_CT != 0 { ConversionTime::Ms800 } else { ConversionTime::Ms100 },
+            mode: match (bits >> CFG_M_SHIFT) & 0b11 {
+                0b00 => Mode::Shutdown,
+                0b01 => Mode::SingleShot,
+                _ => Mode::Continuous,
+            },
+            latch: if bits & CFG_L != 0 { Latch::Latched } else { Latch::Transparent },
+            polarity: if bits & CFG_POL != 0 { Polarity::ActiveHigh } else { Polarity::ActiveLow },
+            mask_exponent: bits & CFG_ME != 0,
+            fault_count: match bits & CFG_FC_MASK {
+                0b00 => FaultCount::One,
+                0b01 => FaultCount::Two,
+                0b10 => FaultCount::Four,
+                _ => FaultCount::Eight,
+            },
+        }
+    }
+
+    /// Encode as a configuration register value
+    pub fn bits(&self) -> u16 {
+        let rn = match self.range {
+            Range::Auto => CFG_RN_AUTO,
+            Range::Fixed(rn) => rn.min(11) as u16,
+        };
+        let mode: u16 = match self.mode {
+            Mode::Shutdown => 0b00,
+            Mode::SingleShot => 0b01,
+            Mode::Continuous => 0b10,
+        };
+        let fc: u16 = match self.fault_count {
+            FaultCount::One => 0b00,
+            FaultCount::Two => 0b01,
+            FaultCount::Four => 0b10,
+            FaultCount::Eight => 0b11,
+        };
+        let mut bits = (rn << CFG_RN_SHIFT) | (mode << CFG_M_SHIFT) | fc;
+        if self.conversion_time == ConversionTime::Ms800 {
+            bits |= CFG_CT;
+        }
+        if self.latch == Latch::Latched {
+            bits |= CFG_L;
+        }
+        if self.polarity == Polarity::ActiveHigh {
+            bits |= CFG_POL;
+        }
+        if self.mask_exponent {
+            bits |= CFG_ME;
+        }
+        bits
+    }
+}
 
 /// For interacting with a OPT3001 sensor
-pub struct DeviceOpt3001<USCI: EUsciI2CBus>{
-    i2c_pin: SDL<USCI>,
+pub struct DeviceOpt3001<I2C>{
+    i2c: I2C,
     address: u8,
-    active_reg: u8,
 }
 
 /// Sensor result converted into units of lux
+///
+/// Ordered by value, so thresholds can be compared directly. Sums saturate rather than wrap.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Debug)]
 pub struct Lux{
     /// integer component of lux measurement, range: 83865-0
     pub whole:u32,
@@ -20,63 +270,367 @@ pub struct Lux{
     pub frac: u8
 }
 
+impl Lux {
+    /// From hundredths of a lux, the sensor's finest resolution
+    pub const fn from_centilux(centilux: u32) -> Lux {
+        Lux { whole: centilux / 100, frac: (centilux % 100) as u8 }
+    }
+
+    /// Value in hundredths of a lux
+    pub const fn centilux(&self) -> u32 {
+        self.whole.saturating_mul(100).saturating_add(self.frac as u32)
+    }
+
+    /// From thousandths of a lux, rounding down to the nearest centilux
+    pub const fn from_millilux(millilux: u32) -> Lux {
+        Lux::from_centilux(millilux / 10)
+    }
+
+    /// Value in thousandths of a lux
+    pub const fn millilux(&self) -> u32 {
+        self.centilux().saturating_mul(10)
+    }
+
+    /// Value as a float
+    #[cfg(feature = "lux-f32")]
+    pub fn as_f32(&self) -> f32 {
+        self.whole as f32 + self.frac as f32 / 100.0
+    }
+
+    /// Mean of the samples, rounded down. `None` if there are none.
+    pub fn average(samples: &[Lux]) -> Option<Lux> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut mean = CentiluxMean::new(samples.len() as u32);
+        for sample in samples {
+            mean.add(sample.centilux());
+        }
+        Some(Lux::from_centilux(mean.value()))
+    }
+}
+
+/// Mean of a known number of centilux values, rounded down. Each value is divided as it is added,
+/// keeping the remainders, so a u32 is enough however many there are.
+pub(crate) struct CentiluxMean {
+    count: u32,
+    mean: u32,
+    remainder: u32,
+}
+
+impl CentiluxMean {
+    /// `count` must not be 0
+    pub(crate) const fn new(count: u32) -> Self {
+        CentiluxMean { count, mean: 0, remainder: 0 }
+    }
+
+    pub(crate) const fn add(&mut self, centilux: u32) {
+        self.mean += centilux / self.count;
+        self.remainder += centilux % self.count;
+        if self.remainder >= self.count {
+            self.mean += 1;
+            self.remainder -= self.count;
+        }
+    }
+
+    pub(crate) const fn value(&self) -> u32 {
+        self.mean
+    }
+}
+
+impl Add for Lux {
+    type Output = Lux;
+
+    fn add(self, rhs: Lux) -> Lux {
+        Lux::from_centilux(self.centilux().saturating_add(rhs.centilux()))
+    }
+}
+
+impl AddAssign for Lux {
+    fn add_assign(&mut self, rhs: Lux) {
+        *self = *self + rhs;
+    }
+}
+
+impl fmt::Display for Lux {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:02}", self.whole, self.frac)
+    }
+}
+
+// Writing this exponent to the low limit register turns on end-of-conversion mode
+const LIMIT_EOC: u16 = 0xC000;
+
+/// Encode as a result/limit register value, rounding down to what the register can hold
+const fn lux_to_reading(lux: &Lux) -> u16{
+    let centilux = lux.centilux();
+    let mut exp = 0;
+    while exp < 11 && (centilux >> exp) > 0x0FFF {
+        exp += 1;
+    }
+    let mantissa = if centilux >> exp > 0x0FFF { 0x0FFF } else { centilux >> exp };
+    ((exp as u16) << 12) | (mantissa as u16)
+}
+
+/// Decode a result/limit register value: 0.01 lux * 2^exponent * mantissa
 #[inline]
-fn reading_to_lux(val: u16) -> Lux{
+const fn reading_to_lux(val: u16) -> Lux{
     let exp = (val & 0xF000) >> 12;
     let lsb_size : u32 = 0x1 << exp;
-    let lux_raw : u32 = lsb_size * ((val & 0x0FFF) as u32);
-    let whole = lux_raw / 100;
-    Lux{
-        whole,
-        frac: ((lux_raw - (100 * whole)) as u8)
-    }
-}
-
-impl<USCI: EUsciI2CBus> DeviceOpt3001<USCI> {
-    pub fn new(mut sdl_pin: SDL<USCI>) -> Result<DeviceOpt3001<USCI>, I2CErr>{
-        // address:  1000100 (0x44)
-        // config reg (0x1):
-        // 15:12 : 1100
-        // 11 : 0
-        // 10:9 : 10
-        // 8:5 : dc
-        // 4 : 1
-        // 3 : 0
-        // 2 : 0
-        // 1:0 : 00
-        let config_cmd: [u8; 3] = [0x1, 0xC4, 0x10];
-        let res : Result<(), I2CErr> = sdl_pin.write(OPT3001_ADDRESS, &config_cmd)
-            .and_then(|_| {sdl_pin.write(OPT3001_ADDRESS, &[0x00u8])});
-        return match res {
-            Ok(()) => {
-                Ok(DeviceOpt3001 {
-                    i2c_pin: sdl_pin,
-                    address: OPT3001_ADDRESS,
-                    active_reg: 0,
-                })
-            }
-            Err(err) => {
-                Err(err)
-            }
+    Lux::from_centilux(lsb_size * ((val & 0x0FFF) as u32))
+}
+
+// Datasheet example result, and limits encoded with the smallest exponent that fits
+const _: () = {
+    assert!(reading_to_lux(0x3456).centilux() == 8880);
+    assert!(lux_to_reading(&reading_to_lux(0x2400)) == 0x1800);
+    assert!(lux_to_reading(&Lux::from_centilux(u32::MAX)) == 0xBFFF);
+};
+
+impl<I2C, E> DeviceOpt3001<I2C>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+{
+    /// Checks the sensor's IDs at [`Address::Gnd`], then configures it with [`Config::default`]
+    pub fn new(i2c: I2C) -> Result<DeviceOpt3001<I2C>, Error<E>>{
+        Self::with_address(i2c, Address::Gnd)
+    }
+
+    /// Checks the sensor's IDs, then configures it with [`Config::default`].
+    /// On failure the bus is dropped along with the driver. To keep a shared bus, use
+    /// [`new_unverified`](Self::new_unverified) and [`release`](Self::release) it if
+    /// [`verify`](Self::verify) fails.
+    pub fn with_address(i2c: I2C, address: Address) -> Result<DeviceOpt3001<I2C>, Error<E>>{
+        let mut device = Self::new_unverified(i2c, address);
+        device.verify()?;
+        device.set_config(Config::default())?;
+        Ok(device)
+    }
+
+    /// Wraps the bus without talking to the sensor, leaving its configuration as it is
+    pub fn new_unverified(i2c: I2C, address: Address) -> DeviceOpt3001<I2C>{
+        DeviceOpt3001 {
+            i2c,
+            address: address as u8,
         }
     }
 
-    /// Blocking I2C read to get light value from sensor
-    pub fn read_light(&mut self) -> Result<Lux, I2CErr>{
-        if self.active_reg != 0 {
-            match self.i2c_pin.write(self.address, &[0x0u8]) {
-                Ok(_) => {self.active_reg = 0;}
-                Err(err) => {return Err(err)},
-            }
+    /// Check that the device is an OPT3001
+    pub fn verify(&mut self) -> Result<(), Error<E>>{
+        let manufacturer = self.read_register(REG_MANUFACTURER_ID)?;
+        if manufacturer != MANUFACTURER_ID {
+            return Err(Error::WrongManufacturer(manufacturer));
+        }
+        let device = self.read_register(REG_DEVICE_ID)?;
+        if device != DEVICE_ID {
+            return Err(Error::WrongDevice(device));
         }
+        Ok(())
+    }
+
+    /// Blocking I2C read to get light value from sensor.
+    /// This is the latest result, even if no conversion has finished yet. Use
+    /// [`poll_measurement`](Self::poll_measurement) to wait for a new one.
+    pub fn read_light(&mut self) -> Result<Lux, Error<E>>{
+        self.read_register(REG_RESULT).map(reading_to_lux)
+    }
+
+    /// Read a 16-bit register, using a repeated start to set the register pointer.
+    /// Registers are sent most significant byte first.
+    pub fn read_register(&mut self, reg: u8) -> Result<u16, Error<E>>{
         let mut read_buf : [u8;2] = [0,0];
-        return match self.i2c_pin.read(self.address, &mut read_buf) {
-            Ok(_) => {
-                Ok(reading_to_lux(((read_buf[1] as u16) << 8) | (read_buf[0] as u16)))
+        self.i2c.write_read(self.address, &[reg], &mut read_buf).map_err(Error::I2c)?;
+        Ok(u16::from_be_bytes(read_buf))
+    }
+
+    /// Write a 16-bit register, most significant byte first
+    pub fn write_register(&mut self, reg: u8, val: u16) -> Result<(), Error<E>>{
+        let [msb, lsb] = val.to_be_bytes();
+        self.i2c.write(self.address, &[reg, msb, lsb]).map_err(Error::I2c)
+    }
+
+    /// Note that reading the configuration register, which this and the setters do, clears
+    /// [`Status::conversion_ready`] and, in latched mode, the limit flags.
+    pub fn config(&mut self) -> Result<Config, Error<E>>{
+        self.read_register(REG_CONFIG).map(Config::from_bits)
+    }
+
+    /// Read the flags, clearing them as described for [`config`](Self::config)
+    pub fn status(&mut self) -> Result<Status, Error<E>>{
+        self.read_register(REG_CONFIG).map(Status::from_bits)
+    }
+
+    pub fn set_config(&mut self, config: Config) -> Result<(), Error<E>>{
+        self.write_register(REG_CONFIG, config.bits())
+    }
+
+    /// Read the configuration register, change it and write it back
+    pub fn modify_config<F: FnOnce(&mut Config)>(&mut self, f: F) -> Result<(), Error<E>>{
+        let mut config = self.config()?;
+        f(&mut config);
+        self.set_config(config)
+    }
+
+    pub fn range(&mut self) -> Result<Range, Error<E>>{
+        Ok(self.config()?.range)
+    }
+
+    pub fn set_range(&mut self, range: Range) -> Result<(), Error<E>>{
+        self.modify_config(|config| config.range = range)
+    }
+
+    pub fn conversion_time(&mut self) -> Result<ConversionTime, Error<E>>{
+        Ok(self.config()?.conversion_time)
+    }
+
+    pub fn set_conversion_time(&mut self, conversion_time: ConversionTime) -> Result<(), Error<E>>{
+        self.modify_config(|config| config.conversion_time = conversion_time)
+    }
+
+    pub fn mode(&mut self) -> Result<Mode, Error<E>>{
+        Ok(self.config()?.mode)
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) -> Result<(), Error<E>>{
+        self.modify_config(|config| config.mode = mode)
+    }
+
+    pub fn latch(&mut self) -> Result<Latch, Error<E>>{
+        Ok(self.config()?.latch)
+    }
+
+    pub fn set_latch(&mut self, latch: Latch) -> Result<(), Error<E>>{
+        self.modify_config(|config| config.latch = latch)
+    }
+
+    pub fn polarity(&mut self) -> Result<Polarity, Error<E>>{
+        Ok(self.config()?.polarity)
+    }
+
+    pub fn set_polarity(&mut self, polarity: Polarity) -> Result<(), Error<E>>{
+        self.modify_config(|config| config.polarity = polarity)
+    }
+
+    pub fn mask_exponent(&mut self) -> Result<bool, Error<E>>{
+        Ok(self.config()?.mask_exponent)
+    }
+
+    pub fn set_mask_exponent(&mut self, mask_exponent: bool) -> Result<(), Error<E>>{
+        self.modify_config(|config| config.mask_exponent = mask_exponent)
+    }
+
+    pub fn fault_count(&mut self) -> Result<FaultCount, Error<E>>{
+        Ok(self.config()?.fault_count)
+    }
+
+    pub fn set_fault_count(&mut self, fault_count: FaultCount) -> Result<(), Error<E>>{
+        self.modify_config(|config| config.fault_count = fault_count)
+    }
+
+    /// Start a single conversion, after which the sensor shuts down again.
+    /// Collect the result with [`poll_measurement`](Self::poll_measurement).
+    pub fn start_measurement(&mut self) -> Result<(), Error<E>>{
+        self.set_mode(Mode::SingleShot)
+    }
+
+    /// The result, once a conversion has finished since the configuration register was last read.
+    /// Works in continuous mode too, returning each result once.
+    pub fn poll_measurement(&mut self) -> nb::Result<Lux, Error<E>>{
+        if !self.status()?.conversion_ready {
+            return Err(nb::Error::WouldBlock);
+        }
+        Ok(self.read_light()?)
+    }
+
+    /// Start a single conversion and wait up to `timeout_ms` for it, checking every 10 ms.
+    /// A conversion takes around 100 or 800 ms depending on [`ConversionTime`].
+    pub fn measure<D: DelayMs<u16>>(&mut self, delay: &mut D, timeout_ms: u16) -> Result<Lux, Error<E>>{
+        const POLL_MS: u16 = 10;
+        self.start_measurement()?;
+        let mut waited = 0;
+        loop {
+            match self.poll_measurement() {
+                Ok(lux) => return Ok(lux),
+                Err(nb::Error::Other(err)) => return Err(err),
+                Err(nb::Error::WouldBlock) if waited >= timeout_ms => return Err(Error::Timeout),
+                Err(nb::Error::WouldBlock) => {
+                    delay.delay_ms(POLL_MS);
+                    waited = waited.saturating_add(POLL_MS);
+                }
             }
-            Err(err) => { Err(err) }
         }
     }
+
+    /// Set the window outside of which the flags and INT pin go active.
+    /// Limits are rounded down to the register's resolution, which is coarser for brighter limits.
+    pub fn set_limits(&mut self, low: Lux, high: Lux) -> Result<(), Error<E>>{
+        self.write_register(REG_LOW_LIMIT, lux_to_reading(&low))?;
+        self.write_register(REG_HIGH_LIMIT, lux_to_reading(&high))
+    }
+
+    /// The low and high limits. Meaningless in end-of-conversion mode.
+    pub fn limits(&mut self) -> Result<(Lux, Lux), Error<E>>{
+        let low = self.read_register(REG_LOW_LIMIT)?;
+        let high = self.read_register(REG_HIGH_LIMIT)?;
+        Ok((reading_to_lux(low), reading_to_lux(high)))
+    }
+
+    /// Make the INT pin go active after every conversion instead of on limits, until
+    /// [`set_limits`](Self::set_limits) is called again
+    pub fn set_end_of_conversion_mode(&mut self) -> Result<(), Error<E>>{
+        self.write_register(REG_LOW_LIMIT, LIMIT_EOC)
+    }
+
+    pub fn end_of_conversion_mode(&mut self) -> Result<bool, Error<E>>{
+        Ok(self.read_register(REG_LOW_LIMIT)? & LIMIT_EOC == LIMIT_EOC)
+    }
+
+    /// Give back the I2C bus, e.g. to talk to other devices on it
+    pub fn release(self) -> I2C{
+        self.i2c
+    }
+}
+
+// One per address, as each sensor has its own INT pin
+static INT_PENDING: [AtomicBool; 4] = [
+    AtomicBool::new(false),
+    AtomicBool::new(false),
+    AtomicBool::new(false),
+    AtomicBool::new(false),
+];
+
+/// Note that the INT pin of the sensor at `address` went active, call from the pin's port
+/// interrupt handler. Setting up the edge and clearing the pin's interrupt flag is left to the
+/// application, see the [module docs](self#threshold-interrupts).
+#[inline]
+pub fn on_int_interrupt(address: Address){
+    INT_PENDING[address as usize - Address::Gnd as usize].store(true, Relaxed);
 }
 
+/// Whether the INT pin of the sensor at `address` went active since the last call
+#[inline]
+pub fn take_int(address: Address) -> bool{
+    INT_PENDING[address as usize - Address::Gnd as usize].swap(false, Relaxed)
+}
+
+/// Adapts an embedded-hal 1.0 I2C bus to the 0.2 traits the driver uses
+#[cfg(feature = "embedded-hal-1")]
+pub struct Eh1<I2C>(pub I2C);
+
+#[cfg(feature = "embedded-hal-1")]
+impl<I2C: embedded_hal_1::i2c::I2c> Write for Eh1<I2C> {
+    type Error = I2C::Error;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        embedded_hal_1::i2c::I2c::write(&mut self.0, address, bytes)
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<I2C: embedded_hal_1::i2c::I2c> WriteRead for Eh1<I2C> {
+    type Error = I2C::Error;
 
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Self::Error> {
+        embedded_hal_1::i2c::I2c::write_read(&mut self.0, address, bytes, buffer)
+    }
+}
@@ -0,0 +1,102 @@
+//! Panic handler and `abort` shim, so applications don't each need their own.
+//!
+//! The handler is picked with cargo features:
+//! - `panic-serial` (default): print the location and message of the panic to the default
+//!   serial console
+//! - `panic-halt`: stop without printing anything. Takes precedence over `panic-serial`, so
+//!   enabling it is enough to silence the default handler.
+//! - `panic-led`: blink the LaunchPad's red LED (P1.0) in a repeating pattern of three short
+//!   flashes. Combined with `panic-serial`, the message is printed first.
+//!
+//! Either way interrupts are disabled first, and with the `crash-record` feature the panic is saved
+//! to FRAM (see [`crash`](crate::crash)) before anything else. If the watchdog is running it will reset the device.
+//! Turn off default features to provide your own `#[panic_handler]`.
+//! An application that doesn't otherwise use this crate needs `use msp430fr2355_boosterpack as _;`
+//! for the handler to be linked in.
+//!
+//! The `abort-shim` feature (default) provides `abort()`, which the compiler emits calls to when
+//! debug assertions are enabled. MSP430 has no meaningful `abort()`, so it panics instead.
+
+#[cfg(any(feature = "panic-serial", feature = "panic-halt", feature = "panic-led"))]
+use core::panic::PanicInfo;
+
+#[cfg(any(feature = "panic-serial", feature = "panic-halt", feature = "panic-led"))]
+#[panic_handler]
+#[cfg_attr(
+    not(any(all(feature = "panic-serial", not(feature = "panic-halt")), feature = "crash-record")),
+    allow(unused_variables)
+)]
+fn panic(info: &PanicInfo) -> ! {
+    // Disable interrupts to prevent further damage.
+    msp430::interrupt::disable();
+    #[cfg(feature = "crash-record")]
+    crate::crash::record_panic(info);
+    #[cfg(all(feature = "panic-serial", not(feature = "panic-halt")))]
+    report(info);
+    #[cfg(feature = "panic-led")]
+    blink();
+    #[cfg(not(feature = "panic-led"))]
+    loop {
+        // Prevent optimizations that can remove this loop.
+        msp430::asm::barrier();
+    }
+}
+
+// Output is sent synchronously, as interrupts are off
+#[cfg(all(feature = "panic-serial", not(feature = "panic-halt")))]
+fn report(info: &PanicInfo) {
+    use crate::serial_utils::{print_bytes, SerialWriter};
+
+    print_bytes(b"\npanicked");
+    if let Some(location) = info.location() {
+        crate::sput!(" at ", location.file(), ":", location.line(), ":", location.column());
+    }
+    print_bytes(b": ");
+    core::fmt::Write::write_fmt(&mut SerialWriter, format_args!("{}", info.message())).ok();
+    print_bytes(b"\n");
+}
+
+#[cfg(feature = "panic-led")]
+const LED: u8 = 1 << 0;
+// PM5CTL0 bit that holds pins in their reset state after power up
+#[cfg(feature = "panic-led")]
+const LOCKLPM5: u16 = 1 << 0;
+
+// Timed with busy loops, so the actual speed depends on MCLK
+#[cfg(feature = "panic-led")]
+const FLASH: u16 = 100;
+#[cfg(feature = "panic-led")]
+const PAUSE: u16 = 600;
+
+#[cfg(feature = "panic-led")]
+fn blink() -> ! {
+    let pmm = unsafe{&*crate::pac::PMM::ptr()};
+    let p1 = unsafe{&*crate::pac::P1::ptr()};
+    // Normally cleared by the HAL, but the panic may have happened before that
+    pmm.pm5ctl0.modify(|r, w| unsafe{w.bits(r.bits() & !LOCKLPM5)});
+    p1.p1dir.modify(|r, w| unsafe{w.bits(r.bits() | LED)});
+    loop {
+        for _ in 0..3 {
+            p1.p1out.modify(|r, w| unsafe{w.bits(r.bits() | LED)});
+            spin(FLASH);
+            p1.p1out.modify(|r, w| unsafe{w.bits(r.bits() & !LED)});
+            spin(FLASH);
+        }
+        spin(PAUSE);
+    }
+}
+
+#[cfg(feature = "panic-led")]
+fn spin(n: u16) {
+    for _ in 0..n {
+        for _ in 0..1000u16 {
+            msp430::asm::barrier();
+        }
+    }
+}
+
+#[cfg(feature = "abort-shim")]
+#[no_mangle]
+extern "C" fn abort() -> ! {
+    panic!();
+}
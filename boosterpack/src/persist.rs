@@ -0,0 +1,111 @@
+//! Values kept in information FRAM, which survives resets, power cycles and reprogramming.
+//!
+//! Declare a [`Persistent`] static in the `.persist` section, which `memory.x` places in
+//! information FRAM (0x1800-0x19FF) without loading anything into it:
+//! ```ignore
+//! #[link_section = ".persist"]
+//! static CALIBRATION: Persistent<[i16; 2]> = Persistent::new([0; 2]);
+//! ```
+//! The initial value is never used. Until something has been stored, or after the layout of the
+//! value changes, [`Persistent::load`] returns `None`.
+//!
+//! Information FRAM is write protected by the DFWP bit in SYSCFG0, which is only cleared
+//! while storing.
+
+use core::cell::UnsafeCell;
+use core::mem::size_of;
+use core::ptr;
+use msp430::interrupt;
+use crate::pac::SYS;
+
+/// Types that can be stored as raw bytes
+///
+/// # Safety
+/// The type must have no padding, and any bit pattern must be a valid value.
+pub unsafe trait Plain: Copy {}
+
+unsafe impl Plain for u8 {}
+unsafe impl Plain for u16 {}
+unsafe impl Plain for u32 {}
+unsafe impl Plain for i8 {}
+unsafe impl Plain for i16 {}
+unsafe impl Plain for i32 {}
+unsafe impl<T: Plain, const N: usize> Plain for [T; N] {}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Stored<T> {
+    magic: u16,
+    value: T,
+    check: u16,
+}
+
+/// A value in information FRAM, checked with a CRC when loaded
+#[repr(C)]
+pub struct Persistent<T: Plain> {
+    cell: UnsafeCell<Stored<T>>,
+}
+
+// Accesses are volatile and stores happen with interrupts disabled
+unsafe impl<T: Plain> Sync for Persistent<T> {}
+
+// Includes the size, so values saved by firmware with a different layout aren't loaded
+const fn magic<T>() -> u16 {
+    0xC0DE ^ (size_of::<T>() as u16)
+}
+
+/// CRC-16/CCITT of the raw bytes of a value
+fn checksum<T: Plain>(value: &T) -> u16 {
+    let bytes = unsafe { core::slice::from_raw_parts(value as *const T as *const u8, size_of::<T>()) };
+    let mut crc: u16 = 0xFFFF;
+    for &byte in bytes {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+// SYSCFG0 bits, writes need the password in the upper byte
+const FRWPPW: u16 = 0xA500;
+const DFWP: u16 = 0x02;
+
+/// Run `f` with information FRAM writable
+fn unlocked<R>(f: impl FnOnce() -> R) -> R {
+    interrupt::free(|_cs| {
+        let sys = unsafe{&*SYS::ptr()};
+        let prev = sys.syscfg0.read().bits() & 0xFF;
+        sys.syscfg0.write(|w| unsafe{w.bits(FRWPPW | (prev & !DFWP))});
+        let res = f();
+        sys.syscfg0.write(|w| unsafe{w.bits(FRWPPW | prev)});
+        res
+    })
+}
+
+impl<T: Plain> Persistent<T> {
+    /// `value` only satisfies the compiler, see the [module docs](self)
+    pub const fn new(value: T) -> Self {
+        Persistent { cell: UnsafeCell::new(Stored { magic: 0, value, check: 0 }) }
+    }
+
+    /// The stored value, if there is a valid one
+    pub fn load(&self) -> Option<T> {
+        let stored = unsafe { ptr::read_volatile(self.cell.get()) };
+        if stored.magic == magic::<T>() && stored.check == checksum(&stored.value) {
+            Some(stored.value)
+        } else {
+            None
+        }
+    }
+
+    pub fn store(&self, value: T) {
+        let stored = Stored { magic: magic::<T>(), value, check: checksum(&value) };
+        unlocked(|| unsafe { ptr::write_volatile(self.cell.get(), stored) });
+    }
+
+    /// Invalidate the stored value, so [`load`](Self::load) returns `None`
+    pub fn clear(&self) {
+        unlocked(|| unsafe { ptr::write_volatile(ptr::addr_of_mut!((*self.cell.get()).magic), 0) });
+    }
+}
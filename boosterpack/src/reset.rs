@@ -0,0 +1,158 @@
+//! Why the device last reset, decoded from the SYSRSTIV register.
+//!
+//! Call [`init`] early in `main`, as reading SYSRSTIV clears the flags it reports, then
+//! [`cause`] from anywhere afterwards. [`log_cause`] writes it to the serial log.
+//!
+//! ```ignore
+//! let cause = reset::init();
+//! // ... set up serial
+//! reset::log_cause();
+//! ```
+
+use portable_atomic::{AtomicU16, Ordering::Relaxed};
+use crate::pac::SYS;
+use crate::{info, warn};
+
+/// Source of the last reset, in the order of the SYSRSTIV vector values
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ResetCause {
+    /// Power-up. The hardware reports this as a brownout reset (BOR).
+    PowerOn,
+    /// RST/NMI pin, e.g. the LaunchPad's reset button or a debugger
+    ResetPin,
+    /// Software brownout reset, PMMSWBOR
+    SoftwareBor,
+    /// Wakeup from LPM3.5 or LPM4.5
+    LpmWakeup,
+    SecurityViolation,
+    /// The supply dropped below the SVSH threshold
+    Brownout,
+    /// Software power-on reset, PMMSWPOR
+    Software,
+    /// Watchdog timeout
+    Watchdog,
+    /// Write to WDTCTL without the password
+    WatchdogPassword,
+    /// Write to an FRAM controller register without the password
+    FramPassword,
+    /// Uncorrectable FRAM bit error
+    FramUncorrectable,
+    /// Instruction fetch from the peripheral area
+    PeripheralFetch,
+    /// Write to a PMM register without the password
+    PmmPassword,
+    /// The FLL lost lock
+    FllUnlock,
+    /// A vector value this module doesn't know about
+    Unknown(u16),
+}
+
+impl ResetCause {
+    /// Decode a SYSRSTIV value, `None` when no reset flag was pending
+    pub fn from_vector(vector: u16) -> Option<Self> {
+        Some(match vector {
+            0x00 => return None,
+            0x02 => ResetCause::PowerOn,
+            0x04 => ResetCause::ResetPin,
+            0x06 => ResetCause::SoftwareBor,
+            0x08 => ResetCause::LpmWakeup,
+            0x0A => ResetCause::SecurityViolation,
+            0x0E => ResetCause::Brownout,
+            0x14 => ResetCause::Software,
+            0x16 => ResetCause::Watchdog,
+            0x18 => ResetCause::WatchdogPassword,
+            0x1A => ResetCause::FramPassword,
+            0x1C => ResetCause::FramUncorrectable,
+            0x1E => ResetCause::PeripheralFetch,
+            0x20 => ResetCause::PmmPassword,
+            0x24 => ResetCause::FllUnlock,
+            other => ResetCause::Unknown(other),
+        })
+    }
+
+    /// The SYSRSTIV value this was decoded from
+    pub fn vector(self) -> u16 {
+        match self {
+            ResetCause::PowerOn => 0x02,
+            ResetCause::ResetPin => 0x04,
+            ResetCause::SoftwareBor => 0x06,
+            ResetCause::LpmWakeup => 0x08,
+            ResetCause::SecurityViolation => 0x0A,
+            ResetCause::Brownout => 0x0E,
+            ResetCause::Software => 0x14,
+            ResetCause::Watchdog => 0x16,
+            ResetCause::WatchdogPassword => 0x18,
+            ResetCause::FramPassword => 0x1A,
+            ResetCause::FramUncorrectable => 0x1C,
+            ResetCause::PeripheralFetch => 0x1E,
+            ResetCause::PmmPassword => 0x20,
+            ResetCause::FllUnlock => 0x24,
+            ResetCause::Unknown(vector) => vector,
+        }
+    }
+
+    /// Whether the reset points at a problem, rather than power-up, the reset pin or a
+    /// deliberate software reset
+    pub fn is_fault(self) -> bool {
+        !matches!(
+            self,
+            ResetCause::PowerOn | ResetCause::ResetPin | ResetCause::SoftwareBor | ResetCause::LpmWakeup | ResetCause::Software
+        )
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ResetCause::PowerOn => "power on",
+            ResetCause::ResetPin => "reset pin",
+            ResetCause::SoftwareBor => "software BOR",
+            ResetCause::LpmWakeup => "LPMx.5 wakeup",
+            ResetCause::SecurityViolation => "security violation",
+            ResetCause::Brownout => "brownout",
+            ResetCause::Software => "software POR",
+            ResetCause::Watchdog => "watchdog timeout",
+            ResetCause::WatchdogPassword => "watchdog password violation",
+            ResetCause::FramPassword => "FRAM password violation",
+            ResetCause::FramUncorrectable => "uncorrectable FRAM error",
+            ResetCause::PeripheralFetch => "peripheral area fetch",
+            ResetCause::PmmPassword => "PMM password violation",
+            ResetCause::FllUnlock => "FLL unlock",
+            ResetCause::Unknown(_) => "unknown",
+        }
+    }
+}
+
+// Vector of the reset cause, NOT_READ until init() has run
+const NOT_READ: u16 = 0xFFFF;
+static CAUSE: AtomicU16 = AtomicU16::new(NOT_READ);
+
+/// Read and clear the reset flags, returning the highest priority one.
+///
+/// Call once, early in `main`. Later calls return the first result.
+pub fn init() -> Option<ResetCause> {
+    if CAUSE.load(Relaxed) == NOT_READ {
+        let sys = unsafe{&*SYS::ptr()};
+        // Each read returns and clears the highest priority flag, drain them so the next
+        // reset starts clean
+        let first = sys.sysrstiv.read().bits();
+        while sys.sysrstiv.read().bits() != 0 {}
+        CAUSE.store(first, Relaxed);
+    }
+    cause()
+}
+
+/// The cause found by [`init`], `None` if it hasn't been called or no flag was set
+pub fn cause() -> Option<ResetCause> {
+    match CAUSE.load(Relaxed) {
+        NOT_READ => None,
+        vector => ResetCause::from_vector(vector),
+    }
+}
+
+/// Log the reset cause, as a warning if it points at a problem
+pub fn log_cause() {
+    match cause() {
+        Some(cause) if cause.is_fault() => warn!(tag: "reset", "{} (0x{:x})", cause.as_str(), cause.vector()),
+        Some(cause) => info!(tag: "reset", "{}", cause.as_str()),
+        None => info!(tag: "reset", "cause unknown"),
+    }
+}
@@ -1,45 +1,697 @@
 //! A few utilities related to serial I/O
+//!
+//! Output can either go through `core::fmt` with [`sprint!`](crate::sprint)/[`sprintln!`](crate::sprintln),
+//! or through the lightweight [`sput!`](crate::sput)/[`sputln!`](crate::sputln) macros, which only
+//! print strings, byte slices and integers using the converters in this module. The latter avoid
+//! pulling the `core::fmt` machinery into the binary.
+//!
+//! Output is buffered and sent from the UART Tx interrupt while interrupts are enabled,
+//! see [`print_bytes`] and [`flush`]. Likewise, input is collected by the UART Rx interrupt into
+//! a buffer that [`get_bytes`], [`read_byte`] and [`read_line`] read from.
+//!
+//! Either UART, `E_USCI_A0` or `E_USCI_A1`, can host a console, used through a [`Console`] handle.
+//! The free functions and macros in this module go to the default console, which is the last one
+//! passed to [`init_serial`]. Until then output is discarded and nothing is received, so it is
+//! safe to print before the serial port is set up.
+//!
+//! # Interrupts
+//! The console needs its UART's interrupt handler to call [`Console::on_interrupt`]. The
+//! `console-a0` and `console-a1` features define the `EUSCI_A0` and `EUSCI_A1` handlers to do
+//! just that. `console-a1`, for the LaunchPad's USB back-channel UART, is on by default. An
+//! application that needs a UART's interrupt for something else leaves its feature off and, if
+//! it also hosts a console there, calls [`Console::on_interrupt`] from its own handler:
+//! ```ignore
+//! #[interrupt]
+//! fn EUSCI_A0(cs: CriticalSection) {
+//!     Console::<E_USCI_A0>::on_interrupt(cs);
+//! }
+//! ```
 
-use core::mem::MaybeUninit;
+use core::cell::{Cell, RefCell, UnsafeCell};
+use core::fmt;
+use core::marker::PhantomData;
 use nb;
 use embedded_hal::prelude::_embedded_hal_blocking_serial_Write;
 use embedded_hal::prelude::_embedded_hal_serial_Read;
+use embedded_hal::prelude::_embedded_hal_serial_Write;
+use portable_atomic::{AtomicBool, AtomicU8, AtomicU16, Ordering::Relaxed};
+use msp430::interrupt::{free, CriticalSection, Mutex};
+use msp430::register::sr;
 use msp430fr2x5x_hal::serial;
-use msp430fr2x5x_hal::serial::{Rx, Tx};
-use crate::pac::E_USCI_A1;
+use msp430fr2x5x_hal::serial::{Rx, SerialUsci, Tx};
+#[cfg(any(feature = "console-a0", feature = "console-a1"))]
+use crate::pac::interrupt;
+use crate::pac::{E_USCI_A0, E_USCI_A1};
+use crate::queuebuf::QueueBuf;
 
-pub static mut RX_GLOBAL: MaybeUninit<Rx<E_USCI_A1>> = MaybeUninit::uninit();
-pub static mut TX_GLOBAL: MaybeUninit<Tx<E_USCI_A1>> = MaybeUninit::uninit();
+/// Size of the serial output buffer, must be a power of two
+pub const TX_BUF_SIZE: usize = 128;
 
-pub fn init_serial(rx: Rx<E_USCI_A1>, tx: Tx<E_USCI_A1>){
-    unsafe{RX_GLOBAL.write(rx);}
-    unsafe{TX_GLOBAL.write(tx);}
+/// Size of the serial input buffer, must be a power of two
+pub const RX_BUF_SIZE: usize = 64;
+
+// UCAxIFG / UCAxIE bits
+const UCRXIFG: u16 = 0x01;
+const UCTXIFG: u16 = 0x02;
+
+/// State of the console on one UART
+#[doc(hidden)]
+pub struct Port<USCI: SerialUsci> {
+    tx: Mutex<RefCell<Option<Tx<USCI>>>>,
+    rx: Mutex<RefCell<Option<Rx<USCI>>>>,
+    tx_buf: Mutex<UnsafeCell<QueueBuf<TX_BUF_SIZE>>>,
+    rx_buf: Mutex<UnsafeCell<QueueBuf<RX_BUF_SIZE>>>,
+    ready: AtomicBool,
+    overrun: AtomicU16,
+    framing: AtomicU16,
+    parity: AtomicU16,
+    dropped: AtomicU16,
+    // Set when a line ended with '\r', so a following '\n' isn't read as an empty line
+    skip_lf: AtomicBool,
+    // While set, received bytes go here instead of the input buffer
+    rx_hook: Mutex<Cell<Option<RxHook>>>,
 }
 
-/// Requires initialized serial
-#[inline]
-pub fn print_bytes(bytes:&[u8]){
-    unsafe {TX_GLOBAL.assume_init_mut()}.bwrite_all(bytes).ok();
+/// Handler for received bytes, see [`Console::set_rx_hook`]
+pub type RxHook = fn(CriticalSection, u8);
+
+impl<USCI: SerialUsci> Port<USCI> {
+    const fn new() -> Self {
+        Port {
+            tx: Mutex::new(RefCell::new(None)),
+            rx: Mutex::new(RefCell::new(None)),
+            tx_buf: Mutex::new(UnsafeCell::new(QueueBuf::new([0u8;TX_BUF_SIZE]))),
+            rx_buf: Mutex::new(UnsafeCell::new(QueueBuf::new([0u8;RX_BUF_SIZE]))),
+            ready: AtomicBool::new(false),
+            overrun: AtomicU16::new(0),
+            framing: AtomicU16::new(0),
+            parity: AtomicU16::new(0),
+            dropped: AtomicU16::new(0),
+            skip_lf: AtomicBool::new(false),
+            rx_hook: Mutex::new(Cell::new(None)),
+        }
+    }
 }
 
-/// Requires initialized serial
-pub fn get_bytes(bytes:&mut [u8]) -> Result<(), ()>{
-    let rx = unsafe{RX_GLOBAL.assume_init_mut()};
-    for i in 0..bytes.len() {
-        match nb::block!(rx.read()) {
-            Ok(data) => {
-                bytes[i] = data;
+/// UARTs that can host a [`Console`]
+pub trait ConsoleUsci: SerialUsci + Sized + 'static {
+    #[doc(hidden)]
+    const ID: u8;
+    #[doc(hidden)]
+    fn port() -> &'static Port<Self>;
+    /// Interrupt flags that are both set and enabled
+    #[doc(hidden)]
+    fn pending() -> u16;
+}
+
+const NO_CONSOLE: u8 = 0;
+const CONSOLE_A0: u8 = 1;
+const CONSOLE_A1: u8 = 2;
+
+macro_rules! console_usci {
+    ($USCI:ident, $PORT:ident, $id:expr, $ifg:ident, $ie:ident) => {
+        static $PORT: Port<$USCI> = Port::new();
+
+        impl ConsoleUsci for $USCI {
+            const ID: u8 = $id;
+
+            #[inline(always)]
+            fn port() -> &'static Port<Self> {
+                &$PORT
+            }
+
+            #[inline(always)]
+            fn pending() -> u16 {
+                let usci = unsafe{&*$USCI::ptr()};
+                usci.$ifg.read().bits() & usci.$ie.read().bits()
             }
-            Err(serial::RecvError::Overrun(_data)) => {
-                // bytes[i] = data;
-                // return Err(());
+        }
+    };
+}
+
+console_usci!(E_USCI_A0, PORT_A0, CONSOLE_A0, uca0ifg, uca0ie);
+console_usci!(E_USCI_A1, PORT_A1, CONSOLE_A1, uca1ifg, uca1ie);
+
+/// Console used by the free functions and macros
+static DEFAULT_CONSOLE: AtomicU8 = AtomicU8::new(NO_CONSOLE);
+
+/// Handle to the serial console on a UART.
+///
+/// The UART itself is owned by a global, so handles hold no data and can be copied freely or
+/// recreated with [`Console::get`]. Before [`Console::init`] output is discarded and nothing
+/// is received.
+pub struct Console<USCI: ConsoleUsci> {
+    _usci: PhantomData<USCI>,
+}
+
+// Derived impls would require USCI: Copy
+impl<USCI: ConsoleUsci> Clone for Console<USCI> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<USCI: ConsoleUsci> Copy for Console<USCI> {}
+
+impl<USCI: ConsoleUsci> Console<USCI> {
+    /// Take over the UART. Rx interrupts are enabled here, but nothing is received until
+    /// interrupts are enabled globally.
+    pub fn init(mut rx: Rx<USCI>, tx: Tx<USCI>) -> Self {
+        rx.enable_rx_interrupts();
+        let port = USCI::port();
+        free(|cs| {
+            *port.rx.borrow(cs).borrow_mut() = Some(rx);
+            *port.tx.borrow(cs).borrow_mut() = Some(tx);
+        });
+        port.ready.store(true, Relaxed);
+        Self::get()
+    }
+
+    /// Get a handle to the console, whether or not it has been initialized
+    pub const fn get() -> Self {
+        Console { _usci: PhantomData }
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        USCI::port().ready.load(Relaxed)
+    }
+
+    /// Make this the console used by the free functions and macros in this module
+    pub fn set_default(&self) {
+        DEFAULT_CONSOLE.store(USCI::ID, Relaxed);
+    }
+
+    /// Give the UART back, e.g. to reconfigure it. Queued output is sent first, queued input is
+    /// kept for the next [`Console::init`].
+    pub fn release(self) -> Option<(Tx<USCI>, Rx<USCI>)> {
+        self.flush();
+        let port = USCI::port();
+        port.ready.store(false, Relaxed);
+        free(|cs| {
+            let mut rx = port.rx.borrow(cs).borrow_mut();
+            let mut tx = port.tx.borrow(cs).borrow_mut();
+            // Both halves are set together, but don't lose one if the other is missing
+            if rx.is_none() || tx.is_none() {
+                return None;
+            }
+            let mut rx = rx.take()?;
+            rx.disable_rx_interrupts();
+            Some((tx.take()?, rx))
+        })
+    }
+
+    // The Tx half and output buffer, if initialized and not already in use further up the stack
+    // (e.g. when a panic happens while printing)
+    fn with_tx<R>(cs: CriticalSection, f: impl FnOnce(&mut Tx<USCI>, &mut QueueBuf<TX_BUF_SIZE>) -> R) -> Option<R> {
+        let port = USCI::port();
+        let mut tx = port.tx.borrow(cs).try_borrow_mut().ok()?;
+        let tx_buf : &mut QueueBuf<TX_BUF_SIZE> = unsafe{&mut *port.tx_buf.borrow(cs).get()};
+        Some(f(tx.as_mut()?, tx_buf))
+    }
+
+    // Send everything queued, leaving the Tx interrupt off
+    fn drain(tx: &mut Tx<USCI>, tx_buf: &mut QueueBuf<TX_BUF_SIZE>) {
+        while tx_buf.has_data() {
+            nb::block!(tx.write(tx_buf.get())).ok();
+        }
+        tx.disable_tx_interrupts();
+        nb::block!(tx.flush()).ok();
+    }
+
+    /// Queue bytes to be sent by the UART Tx interrupt. Only blocks if the buffer fills up.
+    ///
+    /// When interrupts are disabled (e.g. in a panic handler or an interrupt handler) nothing would
+    /// drain the buffer, so anything still queued is flushed and `bytes` are sent synchronously.
+    pub fn print_bytes(&self, bytes:&[u8]){
+        if !self.is_initialized() {
+            return;
+        }
+        if !sr::read().gie() {
+            free(|cs| Self::with_tx(cs, |tx, tx_buf| {
+                Self::drain(tx, tx_buf);
+                tx.bwrite_all(bytes).ok();
+            }));
+            return;
+        }
+        for &byte in bytes {
+            free(|cs| Self::with_tx(cs, |tx, tx_buf| {
+                if tx_buf.is_full() {
+                    nb::block!(tx.write(tx_buf.get())).ok();
+                }
+                tx_buf.put(byte);
+                tx.enable_tx_interrupts();
+            }));
+        }
+    }
+
+    /// Like [`print_bytes`](Self::print_bytes), but queued in a single critical section so
+    /// output from interrupt handlers can't land in the middle. Keep `bytes` short: if the buffer
+    /// fills up, interrupts stay off while the UART makes room.
+    pub fn print_bytes_atomic(&self, bytes:&[u8]){
+        if !self.is_initialized() {
+            return;
+        }
+        if !sr::read().gie() {
+            self.print_bytes(bytes);
+            return;
+        }
+        free(|cs| Self::with_tx(cs, |tx, tx_buf| {
+            for &byte in bytes {
+                if tx_buf.is_full() {
+                    nb::block!(tx.write(tx_buf.get())).ok();
+                }
+                tx_buf.put(byte);
+            }
+            tx.enable_tx_interrupts();
+        }));
+    }
+
+    /// Block until all queued output has been sent. Works with interrupts disabled.
+    pub fn flush(&self){
+        free(|cs| Self::with_tx(cs, Self::drain));
+    }
+
+    pub fn rx_errors(&self) -> RxErrors {
+        let port = USCI::port();
+        RxErrors {
+            overrun: port.overrun.load(Relaxed),
+            framing: port.framing.load(Relaxed),
+            parity: port.parity.load(Relaxed),
+            dropped: port.dropped.load(Relaxed),
+        }
+    }
+
+    pub fn clear_rx_errors(&self) {
+        let port = USCI::port();
+        port.overrun.store(0, Relaxed);
+        port.framing.store(0, Relaxed);
+        port.parity.store(0, Relaxed);
+        port.dropped.store(0, Relaxed);
+    }
+
+    // Read the UART directly, counting errors
+    fn poll(rx: &mut Rx<USCI>) -> Option<u8> {
+        let port = USCI::port();
+        match rx.read() {
+            Ok(data) => Some(data),
+            Err(nb::Error::Other(serial::RecvError::Overrun(data))) => {
+                // The byte in the buffer is still good, only the one before it was lost
+                port.overrun.add(1, Relaxed);
+                Some(data)
             }
-            Err(_) => {
-                return Err(());
+            Err(nb::Error::Other(serial::RecvError::Framing)) => {
+                port.framing.add(1, Relaxed);
+                None
             }
+            Err(nb::Error::Other(serial::RecvError::Parity)) => {
+                port.parity.add(1, Relaxed);
+                None
+            }
+            Err(nb::Error::WouldBlock) => None,
+        }
+    }
+
+    /// Get the next received byte, if there is one.
+    ///
+    /// With interrupts disabled the UART is polled directly instead of the input buffer.
+    pub fn read_byte(&self) -> Option<u8>{
+        if !self.is_initialized() {
+            return None;
+        }
+        let polling = !sr::read().gie();
+        let port = USCI::port();
+        free(|cs| {
+            if polling {
+                let mut rx = port.rx.borrow(cs).try_borrow_mut().ok()?;
+                return Self::poll(rx.as_mut()?);
+            }
+            let rx_buf : &mut QueueBuf<RX_BUF_SIZE> = unsafe{&mut *port.rx_buf.borrow(cs).get()};
+            if rx_buf.has_data() {
+                Some(rx_buf.get())
+            } else {
+                None
+            }
+        })
+    }
+
+    #[inline]
+    fn read_byte_blocking(&self) -> u8{
+        loop {
+            if let Some(data) = self.read_byte() {
+                return data;
+            }
+        }
+    }
+
+    /// Block until `bytes` has been filled.
+    /// Receive errors are counted rather than returned, see [`Console::rx_errors`].
+    /// Fails straight away if the console isn't initialized.
+    pub fn get_bytes(&self, bytes:&mut [u8]) -> Result<(), ()>{
+        if !self.is_initialized() {
+            return Err(());
+        }
+        for byte in bytes.iter_mut() {
+            *byte = self.read_byte_blocking();
+        }
+        Ok(())
+    }
+
+    /// Block until a line has been entered, echoing input back.
+    /// Backspace and delete erase the last character, anything else that isn't printable ASCII is
+    /// ignored, as are characters past the end of `buf`.
+    /// Returns the length of the line, excluding the line ending, or 0 if the console isn't
+    /// initialized.
+    pub fn read_line(&self, buf:&mut [u8]) -> usize{
+        if !self.is_initialized() {
+            return 0;
+        }
+        let skip_lf = &USCI::port().skip_lf;
+        let mut len = 0;
+        loop {
+            let data = self.read_byte_blocking();
+            match data {
+                b'\n' if skip_lf.swap(false, Relaxed) => {}
+                b'\r' | b'\n' => {
+                    skip_lf.store(data == b'\r', Relaxed);
+                    self.print_bytes(b"\r\n");
+                    return len;
+                }
+                0x08 | 0x7F => {
+                    skip_lf.store(false, Relaxed);
+                    if len > 0 {
+                        len -= 1;
+                        self.print_bytes(b"\x08 \x08");
+                    }
+                }
+                0x20..=0x7E => {
+                    skip_lf.store(false, Relaxed);
+                    if len < buf.len() {
+                        buf[len] = data;
+                        len += 1;
+                        self.print_bytes(&[data]);
+                    }
+                }
+                _ => {
+                    skip_lf.store(false, Relaxed);
+                }
+            }
+        }
+    }
+
+    /// Pass received bytes to `hook`, called from the Rx interrupt, instead of the input buffer.
+    /// `None` goes back to buffering.
+    pub fn set_rx_hook(&self, hook: Option<RxHook>) {
+        free(|cs| USCI::port().rx_hook.borrow(cs).set(hook));
+    }
+
+    /// Stores a received byte in the input buffer, or passes it to the Rx hook.
+    fn rx_interrupt(cs: CriticalSection){
+        let port = USCI::port();
+        let mut rx = match port.rx.borrow(cs).try_borrow_mut() {
+            Ok(rx) => rx,
+            Err(_) => return,
+        };
+        let rx = match rx.as_mut() {
+            Some(rx) => rx,
+            None => return,
         };
+        if let Some(hook) = port.rx_hook.borrow(cs).get() {
+            hook(cs, rx.read_no_check());
+            return;
+        }
+        if let Some(data) = Self::poll(rx) {
+            let rx_buf : &mut QueueBuf<RX_BUF_SIZE> = unsafe{&mut *port.rx_buf.borrow(cs).get()};
+            if rx_buf.is_full() {
+                port.dropped.add(1, Relaxed);
+            } else {
+                rx_buf.put(data);
+            }
+        }
+    }
+
+    /// Sends the next queued byte when the Tx buffer is empty.
+    fn tx_interrupt(cs: CriticalSection){
+        Self::with_tx(cs, |tx, tx_buf| {
+            if tx_buf.has_data() {
+                tx.write(tx_buf.get()).ok();
+            }
+            if !tx_buf.has_data() {
+                tx.disable_tx_interrupts();
+            }
+        });
+    }
+
+    /// Service the UART's Rx and Tx interrupts. Call from its interrupt handler, unless the
+    /// `console-a0`/`console-a1` feature defines the handler already (see the
+    /// [module docs](self#interrupts)).
+    pub fn on_interrupt(cs: CriticalSection){
+        let pending = USCI::pending();
+        if (pending & UCRXIFG) != 0 {
+            Self::rx_interrupt(cs);
+        }
+        if (pending & UCTXIFG) != 0 {
+            Self::tx_interrupt(cs);
+        }
+    }
+}
+
+impl<USCI: ConsoleUsci> fmt::Write for Console<USCI> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.print_bytes(s.as_bytes());
+        Ok(())
     }
-    Ok(())
+}
+
+/// UART interrupts, for the console and its Rx hook
+#[cfg(feature = "console-a0")]
+#[interrupt]
+fn EUSCI_A0(cs : CriticalSection){
+    Console::<E_USCI_A0>::on_interrupt(cs);
+}
+
+#[cfg(feature = "console-a1")]
+#[interrupt]
+fn EUSCI_A1(cs : CriticalSection){
+    Console::<E_USCI_A1>::on_interrupt(cs);
+}
+
+// Run `$body` with `$console` bound to the default console, or evaluate `$none` if there isn't one
+macro_rules! with_default {
+    (|$console:ident| $body:expr, $none:expr) => {
+        match DEFAULT_CONSOLE.load(Relaxed) {
+            CONSOLE_A0 => {
+                let $console = Console::<E_USCI_A0>::get();
+                $body
+            }
+            CONSOLE_A1 => {
+                let $console = Console::<E_USCI_A1>::get();
+                $body
+            }
+            _ => $none,
+        }
+    };
+}
+
+/// Initialize the console on a UART and make it the default console.
+/// Rx interrupts are enabled here, but nothing is received until interrupts are enabled globally.
+pub fn init_serial<USCI: ConsoleUsci>(rx: Rx<USCI>, tx: Tx<USCI>) -> Console<USCI> {
+    let console = Console::init(rx, tx);
+    console.set_default();
+    console
+}
+
+/// [`Console::print_bytes`] on the default console
+pub fn print_bytes(bytes:&[u8]){
+    with_default!(|console| console.print_bytes(bytes), ())
+}
+
+/// [`Console::print_bytes_atomic`] on the default console
+pub fn print_bytes_atomic(bytes:&[u8]){
+    with_default!(|console| console.print_bytes_atomic(bytes), ())
+}
+
+/// [`Console::flush`] on the default console
+pub fn flush(){
+    with_default!(|console| console.flush(), ())
+}
+
+/// Receive errors counted by the Rx interrupt since the last [`clear_rx_errors`]
+#[derive(Clone, Copy, Default)]
+pub struct RxErrors {
+    /// Hardware overruns, a byte was lost before it could be read
+    pub overrun: u16,
+    pub framing: u16,
+    pub parity: u16,
+    /// Bytes dropped because the input buffer was full
+    pub dropped: u16,
+}
+
+/// [`Console::rx_errors`] on the default console
+pub fn rx_errors() -> RxErrors {
+    with_default!(|console| console.rx_errors(), RxErrors::default())
+}
+
+/// [`Console::clear_rx_errors`] on the default console
+pub fn clear_rx_errors() {
+    with_default!(|console| console.clear_rx_errors(), ())
+}
+
+/// Unit struct for writing formatted text to the default console, see [`init_serial`] and
+/// [`Console::set_default`]. Output is discarded while there is none. A [`Console`] handle
+/// implements `fmt::Write` too, for writing to a particular UART.
+pub struct SerialWriter;
+
+impl fmt::Write for SerialWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        print_bytes(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Types that can be printed to the default console without going through `core::fmt`.
+pub trait Printable {
+    fn print(&self);
+}
+
+impl Printable for str {
+    #[inline]
+    fn print(&self) {
+        print_bytes(self.as_bytes());
+    }
+}
+
+impl Printable for [u8] {
+    #[inline]
+    fn print(&self) {
+        print_bytes(self);
+    }
+}
+
+impl<const N: usize> Printable for [u8; N] {
+    #[inline]
+    fn print(&self) {
+        print_bytes(self);
+    }
+}
+
+impl<T: Printable + ?Sized> Printable for &T {
+    #[inline]
+    fn print(&self) {
+        (**self).print();
+    }
+}
+
+impl Printable for char {
+    fn print(&self) {
+        let mut buf = [0u8; 4];
+        print_bytes(self.encode_utf8(&mut buf).as_bytes());
+    }
+}
+
+impl Printable for DecBuf {
+    #[inline]
+    fn print(&self) {
+        print_bytes(self.as_bytes());
+    }
+}
+
+impl Printable for u8 {
+    fn print(&self) {
+        fmt_u32(*self as u32, Pad::None).print();
+    }
+}
+
+impl Printable for u16 {
+    fn print(&self) {
+        fmt_u32(*self as u32, Pad::None).print();
+    }
+}
+
+impl Printable for u32 {
+    fn print(&self) {
+        fmt_u32(*self, Pad::None).print();
+    }
+}
+
+impl Printable for i8 {
+    fn print(&self) {
+        fmt_i32(*self as i32, Pad::None).print();
+    }
+}
+
+impl Printable for i16 {
+    fn print(&self) {
+        fmt_i32(*self as i32, Pad::None).print();
+    }
+}
+
+impl Printable for i32 {
+    fn print(&self) {
+        fmt_i32(*self, Pad::None).print();
+    }
+}
+
+/// Print to the default console using `core::fmt` formatting, through
+/// [`SerialWriter`](crate::serial_utils::SerialWriter).
+/// Output is discarded until a console is set up.
+#[macro_export]
+macro_rules! sprint {
+    ($($arg:tt)*) => {{
+        ::core::fmt::Write::write_fmt(
+            &mut $crate::serial_utils::SerialWriter,
+            ::core::format_args!($($arg)*)
+        ).ok();
+    }};
+}
+
+/// Same as [`sprint!`](crate::sprint), followed by a newline.
+#[macro_export]
+macro_rules! sprintln {
+    () => {
+        $crate::serial_utils::print_bytes(b"\n")
+    };
+    ($($arg:tt)*) => {{
+        $crate::sprint!($($arg)*);
+        $crate::serial_utils::print_bytes(b"\n");
+    }};
+}
+
+/// Print each argument in turn to the default console, without using `core::fmt`.
+/// Arguments must implement [`Printable`](crate::serial_utils::Printable).
+///
+/// ```ignore
+/// sput!("lux: ", res.whole, ".", &byte_to_dec(res.frac)[1..=2]);
+/// ```
+/// Output is discarded until a console is set up.
+#[macro_export]
+macro_rules! sput {
+    ($($item:expr),* $(,)?) => {{
+        $( $crate::serial_utils::Printable::print(&$item); )*
+    }};
+}
+
+/// Same as [`sput!`](crate::sput), followed by a newline.
+#[macro_export]
+macro_rules! sputln {
+    ($($item:expr),* $(,)?) => {{
+        $crate::sput!($($item),*);
+        $crate::serial_utils::print_bytes(b"\n");
+    }};
+}
+
+/// [`Console::read_byte`] on the default console
+pub fn read_byte() -> Option<u8>{
+    with_default!(|console| console.read_byte(), None)
+}
+
+/// [`Console::get_bytes`] on the default console
+pub fn get_bytes(bytes:&mut [u8]) -> Result<(), ()>{
+    with_default!(|console| console.get_bytes(bytes), Err(()))
+}
+
+/// [`Console::read_line`] on the default console
+pub fn read_line(buf:&mut [u8]) -> usize{
+    with_default!(|console| console.read_line(buf), 0)
 }
 
 /// Convert byte to decimal string representation
@@ -66,7 +718,8 @@ pub fn u16_to_dec(val:u16) -> [u8;5]{
     out_buf
 }
 
-/// Convert int to decimal string representation
+/// Convert int to decimal string representation.
+/// Only the lower 9 digits are kept, see [`u32_to_dec_full`] for the full range.
 pub fn u32_to_dec(val:u32) -> [u8;9]{
     let mut out_buf: [u8;9] = [0;9];
     let mut over_ten = val;
@@ -78,6 +731,130 @@ pub fn u32_to_dec(val:u32) -> [u8;9]{
     out_buf
 }
 
+/// Convert int to decimal string representation, covering the full range
+pub fn u32_to_dec_full(val:u32) -> [u8;10]{
+    let mut out_buf: [u8;10] = [0;10];
+    let mut over_ten = val;
+    for i in 0..=9 {
+        let next = over_ten / 10;
+        out_buf[9-i] = ((over_ten - (next * 10) ) as u8) + b'0';
+        over_ten = next;
+    }
+    out_buf
+}
+
+/// Size of [`DecBuf`], enough for a sign, ten digits and a decimal point.
+pub const DEC_BUF_LEN: usize = 12;
+
+/// Padding applied by the variable width formatters.
+/// Widths include the sign and decimal point, and are capped at [`DEC_BUF_LEN`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Pad {
+    /// Leading zeros suppressed, no padding
+    None,
+    /// Pad with leading zeros (after the sign) up to the given width
+    Zeros(u8),
+    /// Pad with leading spaces (before the sign) up to the given width
+    Spaces(u8),
+}
+
+/// Stack buffer holding a number formatted by [`fmt_u32`], [`fmt_i32`] or [`fmt_fixed`]
+#[derive(Clone, Copy)]
+pub struct DecBuf {
+    buf: [u8; DEC_BUF_LEN],
+    len: u8,
+}
+
+impl DecBuf {
+    /// The formatted characters
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[DEC_BUF_LEN - (self.len as usize)..]
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Characters are added right to left
+    #[inline]
+    fn push_front(&mut self, ch: u8) {
+        self.len += 1;
+        self.buf[DEC_BUF_LEN - (self.len as usize)] = ch;
+    }
+}
+
+fn fmt_dec(mut mag: u32, negative: bool, frac_digits: u8, pad: Pad) -> DecBuf {
+    let mut out = DecBuf { buf: [b' '; DEC_BUF_LEN], len: 0 };
+    // Always print at least one digit before the point
+    let min_digits = frac_digits + 1;
+    let mut digits = 0u8;
+    while mag != 0 || digits < min_digits {
+        if frac_digits != 0 && digits == frac_digits {
+            out.push_front(b'.');
+        }
+        let next = mag / 10;
+        out.push_front(((mag - (next * 10)) as u8) + b'0');
+        mag = next;
+        digits += 1;
+    }
+    let sign_len = negative as u8;
+    match pad {
+        Pad::Zeros(width) => {
+            let width = width.min(DEC_BUF_LEN as u8);
+            while out.len + sign_len < width {
+                out.push_front(b'0');
+            }
+            if negative {
+                out.push_front(b'-');
+            }
+        }
+        Pad::Spaces(width) => {
+            if negative {
+                out.push_front(b'-');
+            }
+            let width = width.min(DEC_BUF_LEN as u8);
+            while out.len < width {
+                out.push_front(b' ');
+            }
+        }
+        Pad::None => {
+            if negative {
+                out.push_front(b'-');
+            }
+        }
+    }
+    out
+}
+
+/// Convert an unsigned number to its decimal representation
+pub fn fmt_u32(val: u32, pad: Pad) -> DecBuf {
+    fmt_dec(val, false, 0, pad)
+}
+
+/// Convert a signed number to its decimal representation.
+/// Smaller types can simply be cast, e.g. `fmt_i32(val as i32, Pad::None)`.
+pub fn fmt_i32(val: i32, pad: Pad) -> DecBuf {
+    fmt_dec(val.unsigned_abs(), val < 0, 0, pad)
+}
+
+/// Convert a fixed-point number to its decimal representation.
+/// `val` is in units of 10^-`frac_digits`, e.g. a temperature in hundredths of a degree:
+/// ```ignore
+/// fmt_fixed(-1234, 2, Pad::None) // "-12.34"
+/// fmt_fixed((lux.whole * 100 + lux.frac as u32) as i32, 2, Pad::Spaces(8)) // "  123.05"
+/// ```
+/// `frac_digits` is limited to 9.
+pub fn fmt_fixed(val: i32, frac_digits: u8, pad: Pad) -> DecBuf {
+    fmt_dec(val.unsigned_abs(), val < 0, frac_digits.min(9), pad)
+}
+
 static HEX_LOOKUP: [u8;16] = [b'0', b'1', b'2', b'3', b'4', b'5', b'6', b'7',
     b'8', b'9', b'A', b'B', b'C', b'D', b'E', b'F'];
 
@@ -0,0 +1,320 @@
+//! Small command shell over the serial console, for poking at peripherals during bring-up.
+//!
+//! Commands are looked up in a table of [`Command`]s that the application provides. Each command
+//! gets a mutable reference to an application defined context (usually a struct holding the
+//! drivers it needs) and the arguments that followed it. `help` is always available and lists
+//! the table. [`Command::peek`], [`Command::poke`], [`Command::gpio`] and [`Command::crash`] can be
+//! added to any table.
+//!
+//! ```ignore
+//! static COMMANDS: &[Command<Ctx>] = &[
+//!     Command { name: "lux", usage: "lux", help: "read the light sensor", run: lux },
+//!     Command::peek(),
+//!     Command::poke(),
+//! ];
+//! Shell::new(COMMANDS).run(&mut ctx);
+//! ```
+
+use crate::serial_utils::{byte_to_hex, print_bytes, read_line, u16_to_hex};
+use crate::sput;
+
+/// Maximum length of an input line
+pub const LINE_LEN: usize = 64;
+/// Maximum number of words on a line, including the command name
+pub const MAX_ARGS: usize = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ShellError {
+    UnknownCommand,
+    /// Fewer arguments than the command needs
+    MissingArg,
+    /// An argument couldn't be parsed or is out of range
+    BadArg,
+    /// The command was understood but didn't work
+    Failed,
+}
+
+impl ShellError {
+    fn as_bytes(&self) -> &'static [u8] {
+        match self {
+            ShellError::UnknownCommand => b"unknown command, try 'help'",
+            ShellError::MissingArg => b"missing argument",
+            ShellError::BadArg => b"bad argument",
+            ShellError::Failed => b"failed",
+        }
+    }
+}
+
+/// Parse a decimal number, or a hexadecimal one prefixed with `0x`
+pub fn parse_u32(s: &str) -> Option<u32> {
+    let (digits, radix) = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => (hex, 16),
+        None => (s, 10),
+    };
+    if digits.is_empty() {
+        return None;
+    }
+    let mut val: u32 = 0;
+    for ch in digits.bytes() {
+        let digit = match ch {
+            b'0'..=b'9' => ch - b'0',
+            b'a'..=b'f' => ch - b'a' + 10,
+            b'A'..=b'F' => ch - b'A' + 10,
+            _ => return None,
+        } as u32;
+        if digit >= radix {
+            return None;
+        }
+        val = val.checked_mul(radix)?.checked_add(digit)?;
+    }
+    Some(val)
+}
+
+/// Parse a number as in [`parse_u32`], with an optional leading `-`
+pub fn parse_i32(s: &str) -> Option<i32> {
+    match s.strip_prefix('-') {
+        Some(mag) => {
+            let mag = parse_u32(mag)?;
+            if mag > (i32::MAX as u32) + 1 {
+                None
+            } else {
+                Some((mag as i32).wrapping_neg())
+            }
+        }
+        None => i32::try_from(parse_u32(s)?).ok(),
+    }
+}
+
+/// The words following a command name
+pub struct Args<'a> {
+    words: [&'a str; MAX_ARGS],
+    len: usize,
+    next: usize,
+}
+
+impl<'a> Args<'a> {
+    fn split(line: &'a str) -> Self {
+        let mut args = Args { words: [""; MAX_ARGS], len: 0, next: 0 };
+        for word in line.split_ascii_whitespace().take(MAX_ARGS) {
+            args.words[args.len] = word;
+            args.len += 1;
+        }
+        args
+    }
+
+    /// Number of arguments that haven't been taken yet
+    pub fn remaining(&self) -> usize {
+        self.len - self.next
+    }
+
+    /// Take the next argument, if any
+    pub fn next_opt(&mut self) -> Option<&'a str> {
+        if self.next < self.len {
+            self.next += 1;
+            Some(self.words[self.next - 1])
+        } else {
+            None
+        }
+    }
+
+    /// Take the next argument
+    pub fn next_str(&mut self) -> Result<&'a str, ShellError> {
+        self.next_opt().ok_or(ShellError::MissingArg)
+    }
+
+    /// Take the next argument as a decimal or `0x` hex number
+    pub fn next_u32(&mut self) -> Result<u32, ShellError> {
+        parse_u32(self.next_str()?).ok_or(ShellError::BadArg)
+    }
+
+    pub fn next_u16(&mut self) -> Result<u16, ShellError> {
+        u16::try_from(self.next_u32()?).map_err(|_| ShellError::BadArg)
+    }
+
+    pub fn next_u8(&mut self) -> Result<u8, ShellError> {
+        u8::try_from(self.next_u32()?).map_err(|_| ShellError::BadArg)
+    }
+
+    pub fn next_i32(&mut self) -> Result<i32, ShellError> {
+        parse_i32(self.next_str()?).ok_or(ShellError::BadArg)
+    }
+}
+
+/// An entry in the command table
+pub struct Command<C> {
+    /// Word that invokes the command
+    pub name: &'static str,
+    /// Shown by `help`, e.g. `"adc <ch>"`
+    pub usage: &'static str,
+    /// Shown by `help` after the usage
+    pub help: &'static str,
+    pub run: fn(&mut C, &mut Args) -> Result<(), ShellError>,
+}
+
+impl<C> Command<C> {
+    /// `peek <addr> [w]`: print the byte, or with `w` the word, at an address
+    pub const fn peek() -> Self {
+        Command { name: "peek", usage: "peek <addr> [w]", help: "read memory", run: peek::<C> }
+    }
+
+    /// `poke <addr> <val> [w]`: write a byte, or with `w` a word, to an address
+    pub const fn poke() -> Self {
+        Command { name: "poke", usage: "poke <addr> <val> [w]", help: "write memory", run: poke::<C> }
+    }
+
+    /// `gpio <port.pin> [0|1]`: read a pin, or make it an output and drive it
+    pub const fn gpio() -> Self {
+        Command { name: "gpio", usage: "gpio <port.pin> [0|1]", help: "read or drive a pin", run: gpio::<C> }
+    }
+
+    /// `crash [clear]`: print the saved [crash record](crate::crash), or clear it
+    pub const fn crash() -> Self {
+        Command { name: "crash", usage: "crash [clear]", help: "show or clear the last crash", run: crash::<C> }
+    }
+}
+
+// Checks for the optional word size argument of peek/poke
+fn word_sized(args: &mut Args) -> Result<bool, ShellError> {
+    match args.next_opt() {
+        None | Some("b") => Ok(false),
+        Some("w") => Ok(true),
+        Some(_) => Err(ShellError::BadArg),
+    }
+}
+
+fn peek<C>(_ctx: &mut C, args: &mut Args) -> Result<(), ShellError> {
+    let addr = args.next_u16()?;
+    if word_sized(args)? {
+        if addr & 1 != 0 {
+            return Err(ShellError::BadArg);
+        }
+        let val = unsafe { core::ptr::read_volatile(addr as usize as *const u16) };
+        sput!("0x", u16_to_hex(val), "\r\n");
+    } else {
+        let val = unsafe { core::ptr::read_volatile(addr as usize as *const u8) };
+        sput!("0x", byte_to_hex(val), "\r\n");
+    }
+    Ok(())
+}
+
+fn poke<C>(_ctx: &mut C, args: &mut Args) -> Result<(), ShellError> {
+    let addr = args.next_u16()?;
+    let val = args.next_u16()?;
+    if word_sized(args)? {
+        if addr & 1 != 0 {
+            return Err(ShellError::BadArg);
+        }
+        unsafe { core::ptr::write_volatile(addr as usize as *mut u16, val) };
+    } else {
+        let val = u8::try_from(val).map_err(|_| ShellError::BadArg)?;
+        unsafe { core::ptr::write_volatile(addr as usize as *mut u8, val) };
+    }
+    Ok(())
+}
+
+// Digital I/O registers come in pairs of ports (P1/P2, P3/P4, ...), 0x20 bytes apart,
+// with the odd port at the even address.
+const PORT_BASE: usize = 0x0200;
+const PXIN: usize = 0x00;
+const PXOUT: usize = 0x02;
+const PXDIR: usize = 0x04;
+const NUM_PORTS: u8 = 6;
+
+fn gpio<C>(_ctx: &mut C, args: &mut Args) -> Result<(), ShellError> {
+    let (port, pin) = args.next_str()?.split_once('.').ok_or(ShellError::BadArg)?;
+    let port = parse_u32(port).filter(|p| (1..=NUM_PORTS as u32).contains(p)).ok_or(ShellError::BadArg)? as usize;
+    let pin = parse_u32(pin).filter(|p| *p < 8).ok_or(ShellError::BadArg)?;
+    let base = PORT_BASE + ((port - 1) / 2) * 0x20 + ((port - 1) % 2);
+    let mask = 1u8 << pin;
+    match args.next_opt() {
+        None => {
+            let val = unsafe { core::ptr::read_volatile((base + PXIN) as *const u8) };
+            print_bytes(if val & mask != 0 { b"1\r\n" } else { b"0\r\n" });
+        }
+        Some(level) => {
+            let high = match level {
+                "0" => false,
+                "1" => true,
+                _ => return Err(ShellError::BadArg),
+            };
+            unsafe {
+                let out = (base + PXOUT) as *mut u8;
+                let dir = (base + PXDIR) as *mut u8;
+                let val = core::ptr::read_volatile(out);
+                core::ptr::write_volatile(out, if high { val | mask } else { val & !mask });
+                core::ptr::write_volatile(dir, core::ptr::read_volatile(dir) | mask);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn crash<C>(_ctx: &mut C, args: &mut Args) -> Result<(), ShellError> {
+    match args.next_opt() {
+        None => {
+            if !crate::crash::report() {
+                print_bytes(b"no crash recorded\r\n");
+            }
+        }
+        Some("clear") => crate::crash::clear(),
+        Some(_) => return Err(ShellError::BadArg),
+    }
+    Ok(())
+}
+
+/// Reads lines from the serial console and runs the matching commands
+pub struct Shell<'a, C> {
+    commands: &'a [Command<C>],
+    prompt: &'a [u8],
+}
+
+impl<'a, C> Shell<'a, C> {
+    pub const fn new(commands: &'a [Command<C>]) -> Self {
+        Shell { commands, prompt: b"> " }
+    }
+
+    pub const fn with_prompt(commands: &'a [Command<C>], prompt: &'a [u8]) -> Self {
+        Shell { commands, prompt }
+    }
+
+    /// Run a single line of input
+    pub fn execute(&self, ctx: &mut C, line: &str) -> Result<(), ShellError> {
+        let mut args = Args::split(line);
+        let name = match args.next_opt() {
+            Some(name) => name,
+            None => return Ok(()),
+        };
+        if name == "help" {
+            self.help();
+            return Ok(());
+        }
+        match self.commands.iter().find(|cmd| cmd.name == name) {
+            Some(cmd) => (cmd.run)(ctx, &mut args),
+            None => Err(ShellError::UnknownCommand),
+        }
+    }
+
+    fn help(&self) {
+        print_bytes(b"help\r\n");
+        for cmd in self.commands {
+            sput!("  ", cmd.usage, " - ", cmd.help, "\r\n");
+        }
+    }
+
+    /// Prompt for and run commands forever
+    ///
+    /// Reads from and prints to the default console, see
+    /// [`init_serial`](crate::serial_utils::init_serial). Interrupts must be enabled.
+    pub fn run(&self, ctx: &mut C) -> ! {
+        let mut buf = [0u8; LINE_LEN];
+        loop {
+            print_bytes(self.prompt);
+            let len = read_line(&mut buf);
+            // read_line only stores printable ASCII
+            let line = core::str::from_utf8(&buf[..len]).unwrap_or("");
+            if let Err(err) = self.execute(ctx, line) {
+                sput!("error: ", err.as_bytes(), "\r\n");
+            }
+        }
+    }
+}
@@ -1,4 +1,7 @@
 //! Functions for streaming data over UART.
+//!
+//! The UART is passed in as a [`Console`], so images can be streamed on one UART while the
+//! default console, used for logging, is on the other.
 
 
 use core::{
@@ -13,19 +16,18 @@ use embedded_hal::prelude::_embedded_hal_blocking_spi_Write;
 use msp430fr2355::{interrupt};
 use msp430::{
     asm,
-    interrupt::{Mutex}
+    interrupt::{CriticalSection, Mutex}
 };
 use msp430fr2355::E_USCI_B1;
 use msp430fr2x5x_hal::{
     spi::SPIPins,
 };
 use msp430fr2x5x_hal::gpio::{Output, Pin, P3, Pin2};
-use portable_atomic::{AtomicU16};
+use portable_atomic::AtomicU16;
 use st7735_lcd::instruction::Instruction;
 use st7735_lcd::ST7735;
 use crate::{
-    serial_utils,
-    serial_utils::RX_GLOBAL,
+    serial_utils::{Console, ConsoleUsci},
     queuebuf::QueueBuf
 };
 
@@ -59,29 +61,28 @@ fn to_u16(arr: &[u8]) -> u16{
 pub static mut SCREEN_SPI_GLOBAL : MaybeUninit<SPIPins<E_USCI_B1>> = MaybeUninit::uninit();
 pub static mut DC_PIN : MaybeUninit<Pin<P3, Pin2, Output>> = MaybeUninit::uninit();
 
-pub fn request_img<SPI: spi::Write<u8>, DC: OutputPin, RST: OutputPin>
-(num: u16, screen : &mut ST7735<SPI, DC, RST>) {
+pub fn request_img<USCI: ConsoleUsci, SPI: spi::Write<u8>, DC: OutputPin, RST: OutputPin>
+(console: Console<USCI>, num: u16, screen : &mut ST7735<SPI, DC, RST>) {
     let split = to_u8(num);
-    serial_utils::print_bytes(&[0xFFu8, Command::GetImg.into(), split[0], split[1]]);
-    download(screen)
+    console.print_bytes(&[0xFFu8, Command::GetImg.into(), split[0], split[1]]);
+    download(console, screen)
 }
 
-pub fn request_stream<SPI: spi::Write<u8>, DC: OutputPin, RST: OutputPin>
-(screen : &mut ST7735<SPI, DC, RST>) {
-    serial_utils::print_bytes(&[0xFFu8, Command::GetStream.into()]);
-    download(screen)
+pub fn request_stream<USCI: ConsoleUsci, SPI: spi::Write<u8>, DC: OutputPin, RST: OutputPin>
+(console: Console<USCI>, screen : &mut ST7735<SPI, DC, RST>) {
+    console.print_bytes(&[0xFFu8, Command::GetStream.into()]);
+    download(console, screen)
 }
 
-fn download<SPI: spi::Write<u8>, DC: OutputPin, RST: OutputPin>
-    (screen : &mut ST7735<SPI, DC, RST>) {
+fn download<USCI: ConsoleUsci, SPI: spi::Write<u8>, DC: OutputPin, RST: OutputPin>
+    (console: Console<USCI>, screen : &mut ST7735<SPI, DC, RST>) {
     let spi = unsafe{SCREEN_SPI_GLOBAL.assume_init_mut()};
-    let rx = unsafe{RX_GLOBAL.assume_init_mut()};
     let dc = unsafe{DC_PIN.assume_init_mut()};
 
 
     let mut byte_buf = [0u8;6];
-    serial_utils::get_bytes(&mut byte_buf).ok();
-    serial_utils::print_bytes(&[0xAAu8]);
+    console.get_bytes(&mut byte_buf).ok();
+    console.print_bytes(&[0xAAu8]);
 
     let start_x = byte_buf[0] as u16;
     let start_y = byte_buf[1] as u16;
@@ -99,19 +100,19 @@ fn download<SPI: spi::Write<u8>, DC: OutputPin, RST: OutputPin>
     spi.write(&[Instruction::RAMWR as u8]).ok();
     dc.set_high().ok();
 
-    rx.enable_rx_interrupts();
-    serial_utils::print_bytes(&[0xAAu8]);
+    console.set_rx_hook(Some(rx_interrupt));
+    console.print_bytes(&[0xAAu8]);
 
     while BYTES_LEFT.load(Relaxed) != 0 {
         asm::nop();
     }
-    rx.disable_rx_interrupts();
+    console.set_rx_hook(None);
 }
 
-pub fn get_num_images() -> u16{
+pub fn get_num_images<USCI: ConsoleUsci>(console: Console<USCI>) -> u16{
     let mut rd_buf = [0u8;2];
-    serial_utils::print_bytes(&[0xFFu8, Command::GetNumImg.into()]);
-    serial_utils::get_bytes(&mut rd_buf).ok();
+    console.print_bytes(&[0xFFu8, Command::GetNumImg.into()]);
+    console.get_bytes(&mut rd_buf).ok();
     to_u16(&rd_buf)
 }
 
@@ -119,15 +120,13 @@ static SPI_TX_BUF: Mutex<UnsafeCell<QueueBuf<BUF_SIZE>>> =
     Mutex::new(UnsafeCell::new(QueueBuf::new([0u8;BUF_SIZE])));
 static BYTES_LEFT : AtomicU16 = AtomicU16::new(0u16);
 
-/// UART Rx interrupt from USB, forwards data to SPI Tx handler.
-#[interrupt]
-fn EUSCI_A1(cs : CriticalSection){
-    let rx = unsafe{RX_GLOBAL.assume_init_mut()};
+/// Queue a byte received while streaming for the screen, the console's Rx hook while streaming.
+fn rx_interrupt(cs : CriticalSection, data: u8){
     let tx_buf : &mut QueueBuf<BUF_SIZE> = unsafe{&mut *SPI_TX_BUF.borrow(cs).get()};
     let spi = unsafe{SCREEN_SPI_GLOBAL.assume_init_mut()};
 
     spi.tx_interrupt_set(true);
-    tx_buf.put(rx.read_no_check());
+    tx_buf.put(data);
 }
 
 /// SPI Tx interrupt for screen.
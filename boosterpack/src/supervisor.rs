@@ -0,0 +1,173 @@
+//! Watchdog supervision of several tasks.
+//!
+//! Each task registered with a [`Supervisor`] gets a [`Task`] handle to check in with, from
+//! its own loop or an interrupt handler. [`Supervisor::tick`] is called periodically, and only
+//! feeds the watchdog while every task has checked in within its deadline (counted in ticks).
+//! Once a task is overdue it is saved to FRAM and the watchdog is left to reset the device,
+//! after which [`last_overdue`] tells which task it was.
+//!
+//! ```ignore
+//! let mut wdt = Wdt::constrain(periph.WDT_A);
+//! wdt.set_aclk(&aclk).start(WdtClkPeriods::_32K);
+//! let mut supervisor: Supervisor<_, 2> = Supervisor::new(wdt);
+//! let sensor = supervisor.add_task("sensor", 10).unwrap();
+//! let ui = supervisor.add_task("ui", 50).unwrap();
+//! // sensor.check_in() and ui.check_in() from their loops, supervisor.tick() from a timer
+//! ```
+//! The watchdog has to be started beforehand, with a period longer than the time between ticks.
+
+use embedded_hal::watchdog::Watchdog;
+use portable_atomic::{AtomicU16, AtomicU8, Ordering::Relaxed};
+use crate::crash::{self, Text};
+use crate::persist::{Persistent, Plain};
+use crate::{error, sput};
+
+/// Most tasks that can be registered, across all supervisors
+pub const MAX_TASKS: usize = 16;
+/// Longest task name kept in the overdue record
+pub const NAME_LEN: usize = 15;
+
+// One bit per task, set by check-ins and cleared by the supervisor's tick
+static CHECKED_IN: AtomicU16 = AtomicU16::new(0);
+static NUM_TASKS: AtomicU8 = AtomicU8::new(0);
+
+/// Handle a task checks in with
+#[derive(Clone, Copy)]
+pub struct Task {
+    mask: u16,
+}
+
+impl Task {
+    /// Tell the supervisor this task is still running. Safe to call from interrupts.
+    #[inline]
+    pub fn check_in(&self) {
+        CHECKED_IN.or(self.mask, Relaxed);
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Entry {
+    name: &'static str,
+    mask: u16,
+    deadline: u16,
+    // Ticks since the last check-in
+    age: u16,
+}
+
+/// What was saved about the task that missed its deadline
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct OverdueRecord {
+    deadline: u16,
+    boot: u16,
+    name: Text<NAME_LEN>,
+}
+
+unsafe impl Plain for OverdueRecord {}
+
+impl OverdueRecord {
+    /// Name of the task, cut off at [`NAME_LEN`]
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// Deadline of the task in ticks
+    pub fn deadline(&self) -> u16 {
+        self.deadline
+    }
+
+    /// [`crash::boot_count`] during the run the task stalled in
+    pub fn boot(&self) -> u16 {
+        self.boot
+    }
+
+    /// Print the record to the default serial console
+    pub fn print(&self) {
+        sput!("watchdog: task '", self.name(), "' missed its deadline of ", self.deadline,
+            " ticks during boot ", self.boot, "\n");
+    }
+}
+
+#[link_section = ".persist"]
+static OVERDUE: Persistent<OverdueRecord> =
+    Persistent::new(OverdueRecord { deadline: 0, boot: 0, name: Text::new() });
+
+/// The task that last missed its deadline, if one has since [`clear_overdue`]
+pub fn last_overdue() -> Option<OverdueRecord> {
+    OVERDUE.load()
+}
+
+pub fn clear_overdue() {
+    OVERDUE.clear();
+}
+
+/// Feeds a watchdog as long as up to `N` tasks keep checking in
+pub struct Supervisor<W: Watchdog, const N: usize> {
+    wdt: W,
+    tasks: [Entry; N],
+    len: usize,
+    // Bits of all the registered tasks
+    masks: u16,
+    overdue: Option<&'static str>,
+}
+
+impl<W: Watchdog, const N: usize> Supervisor<W, N> {
+    /// `wdt` should already be started
+    pub fn new(wdt: W) -> Self {
+        const EMPTY: Entry = Entry { name: "", mask: 0, deadline: 0, age: 0 };
+        Supervisor { wdt, tasks: [EMPTY; N], len: 0, masks: 0, overdue: None }
+    }
+
+    /// Register a task that has to check in at least once every `deadline` ticks.
+    /// Returns `None` when this supervisor is full, or [`MAX_TASKS`] have been registered.
+    pub fn add_task(&mut self, name: &'static str, deadline: u16) -> Option<Task> {
+        if self.len == N {
+            return None;
+        }
+        let bit = NUM_TASKS.load(Relaxed);
+        if bit as usize == MAX_TASKS {
+            return None;
+        }
+        NUM_TASKS.store(bit + 1, Relaxed);
+        let mask = 1 << bit;
+        // Start from a clean slate in case a previous owner of the bit left it set
+        CHECKED_IN.and(!mask, Relaxed);
+        self.tasks[self.len] = Entry { name, mask, deadline, age: 0 };
+        self.len += 1;
+        self.masks |= mask;
+        Some(Task { mask })
+    }
+
+    /// Age the tasks and feed the watchdog if none of them are overdue.
+    ///
+    /// The first task to miss its deadline is logged and saved to FRAM, and from then on the
+    /// watchdog is never fed, so this keeps returning its name until the device resets.
+    pub fn tick(&mut self) -> Result<(), &'static str> {
+        if let Some(name) = self.overdue {
+            return Err(name);
+        }
+        let checked_in = CHECKED_IN.fetch_and(!self.masks, Relaxed);
+        for task in self.tasks[..self.len].iter_mut() {
+            if checked_in & task.mask != 0 {
+                task.age = 0;
+                continue;
+            }
+            task.age = task.age.saturating_add(1);
+            if task.age > task.deadline {
+                self.overdue = Some(task.name);
+                let mut name = Text::new();
+                name.push_str(task.name);
+                OVERDUE.store(OverdueRecord { deadline: task.deadline, boot: crash::boot_count(), name });
+                error!(tag: "watchdog", "task {} is overdue", task.name);
+                return Err(task.name);
+            }
+        }
+        self.wdt.feed();
+        Ok(())
+    }
+
+    /// Give the watchdog back, e.g. to stop it before sleeping
+    pub fn release(self) -> W {
+        self.wdt
+    }
+}
@@ -0,0 +1,323 @@
+//! Driver for the TMP006 Infrared Thermopile Sensor
+//!
+//! Like the [OPT3001 driver](crate::opt3001), this works with any bus implementing the
+//! embedded-hal 0.2 blocking I2C `Write` and `WriteRead` traits. On the BoosterPack both sensors
+//! share one bus; use `release()` on one driver to hand the bus to the other.
+//!
+//! The sensor measures its own (die) temperature and the voltage produced by the thermopile,
+//! which depends on the temperature of the object it is facing. [`object_temperature`] combines
+//! the two using the calibration polynomial from the TMP006 user's guide (SBOU107), with
+//! integer arithmetic only. Temperatures are in thousandths of a degree Celsius.
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+/// I2C address of the TMP006 on the BoosterPack (ADR0 and ADR1 low).
+/// The other strappings give 0x41 to 0x47.
+pub const DEFAULT_ADDRESS: u8 = 0x40;
+
+// Register addresses, for DeviceTmp006::read_register and write_register
+pub const REG_VOLTAGE: u8 = 0x00;
+pub const REG_DIE_TEMP: u8 = 0x01;
+pub const REG_CONFIG: u8 = 0x02;
+pub const REG_MANUFACTURER_ID: u8 = 0xFE;
+pub const REG_DEVICE_ID: u8 = 0xFF;
+
+/// "TI" in ASCII
+pub const MANUFACTURER_ID: u16 = 0x5449;
+pub const DEVICE_ID: u16 = 0x0067;
+
+/// Typical sensitivity of the thermopile, in units of 1e-18. Calibrate against an object of
+/// known temperature for better accuracy, see [`DeviceTmp006::set_sensitivity`].
+pub const DEFAULT_S0: u32 = 64_000;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Error<E> {
+    /// The bus transfer failed
+    I2c(E),
+    /// Something answered, but with this manufacturer ID instead of [`MANUFACTURER_ID`]
+    WrongManufacturer(u16),
+    /// Something answered, but with this device ID instead of [`DEVICE_ID`]
+    WrongDevice(u16),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    PowerDown,
+    Continuous,
+}
+
+/// Conversions per second. Slower rates average more samples per result, reducing noise.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConversionRate {
+    /// 1 sample per result
+    Hz4,
+    /// 2 samples averaged
+    Hz2,
+    /// 4 samples averaged
+    Hz1,
+    /// 8 samples averaged
+    HalfHz,
+    /// 16 samples averaged
+    QuarterHz,
+}
+
+/// Writable fields of the configuration register (0x02)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Config {
+    pub mode: Mode,
+    pub rate: ConversionRate,
+    /// Drive the DRDY pin low when a conversion finishes
+    pub drdy_pin: bool,
+}
+
+// Configuration register fields
+const CFG_RST: u16 = 1 << 15;
+const CFG_MOD_SHIFT: u16 = 12;
+const CFG_CR_SHIFT: u16 = 9;
+const CFG_EN: u16 = 1 << 8;
+const CFG_DRDY: u16 = 1 << 7;
+
+impl Default for Config {
+    /// Continuous, one conversion per second, DRDY pin off. The power-on configuration.
+    fn default() -> Self {
+        Config {
+            mode: Mode::Continuous,
+            rate: ConversionRate::Hz1,
+            drdy_pin: false,
+        }
+    }
+}
+
+impl Config {
+    /// Decode the configuration register, ignoring the read-only flag
+    pub fn from_bits(bits: u16) -> Self {
+        Config {
+            mode: if (bits >> CFG_MOD_SHIFT) & 0b111 == 0 { Mode::PowerDown } else { Mode::Continuous },
+            rate: match (bits >> CFG_CR_SHIFT) & 0b111 {
+                0b000 => ConversionRate::Hz4,
+                0b001 => ConversionRate::Hz2,
+                0b010 => ConversionRate::Hz1,
+                0b011 => ConversionRate::HalfHz,
+                _ => ConversionRate::QuarterHz,
+            },
+            drdy_pin: bits & CFG_EN != 0,
+        }
+    }
+
+    /// Encode as a configuration register value
+    pub fn bits(&self) -> u16 {
+        let mode: u16 = match self.mode {
+            Mode::PowerDown => 0b000,
+            Mode::Continuous => 0b111,
+        };
+        let rate: u16 = match self.rate {
+            ConversionRate::Hz4 => 0b000,
+            ConversionRate::Hz2 => 0b001,
+            ConversionRate::Hz1 => 0b010,
+            ConversionRate::HalfHz => 0b011,
+            ConversionRate::QuarterHz => 0b100,
+        };
+        let mut bits = (mode << CFG_MOD_SHIFT) | (rate << CFG_CR_SHIFT);
+        if self.drdy_pin {
+            bits |= CFG_EN;
+        }
+        bits
+    }
+}
+
+/// Thermopile voltage register value to nanovolts (156.25 nV per LSB)
+pub const fn voltage_to_nv(raw: u16) -> i32 {
+    (raw as i16 as i32 * 625) / 4
+}
+
+/// Die temperature register value to thousandths of a degree Celsius.
+/// The 14-bit result is left justified, 1/32 of a degree per LSB.
+pub const fn die_temp_to_mc(raw: u16) -> i32 {
+    ((raw as i16 >> 2) as i32 * 125) / 4
+}
+
+/// Object temperature in thousandths of a degree Celsius, from the thermopile voltage in
+/// nanovolts, the die temperature in thousandths of a degree and the sensitivity `s0` in units
+/// of 1e-18. Follows the user's guide:
+/// ```text
+/// S    = S0 (1 + a1 (Tdie - Tref) + a2 (Tdie - Tref)^2)
+/// Vos  = b0 + b1 (Tdie - Tref) + b2 (Tdie - Tref)^2
+/// f    = (Vobj - Vos) + c2 (Vobj - Vos)^2
+/// Tobj = (Tdie^4 + f / S)^(1/4)
+/// ```
+pub const fn object_temperature(voltage_nv: i32, die_mc: i32, s0: u32) -> i32 {
+    const ZERO_C_MK: i64 = 273_150;
+    // Tdie - Tref, with Tref = 25 C, in mK
+    let dt = die_mc as i64 - 25_000;
+    // S / S0 in parts per billion, a1 = 1.75e-3, a2 = -1.678e-5
+    let s_ppb = 1_000_000_000 + dt * 1750 - dt * dt * 16_780 / 1_000_000;
+    // nV, b0 = -2.94e-5, b1 = -5.7e-7, b2 = 4.63e-9
+    let vos = -29_400 - dt * 570 / 1000 + dt * dt * 463 / 100_000_000;
+    // nV, c2 = 13.4
+    let v = voltage_nv as i64 - vos;
+    let f = v + v * v * 134 / 10_000_000_000;
+
+    // Fourth powers are kept in cK^4 (1 cK = 10 mK), so up to 378 C fits in a u64
+    let die_mk = die_mc as i64 + ZERO_C_MK;
+    let die_ck2 = (die_mk * die_mk / 100) as u64;
+    let die4 = die_ck2 * die_ck2;
+    // f / S in cK^4: nV / (1e-18 * 1e-9) = 1e18 K^4 = 1e26 cK^4, split into f / (S / S0) in nV
+    // and that * 1e17 / S0, which is done in two parts to stay within a u64
+    let s_ppb = if s_ppb < 1 { 1 } else { s_ppb };
+    let s0 = if s0 < 1 { 1 } else { s0 as u64 };
+    let scaled = f * 1_000_000_000 / s_ppb * 100_000_000;
+    let magnitude = scaled.unsigned_abs();
+    let term = (magnitude / s0).saturating_mul(1_000_000_000)
+        .saturating_add(magnitude % s0 * 1_000_000_000 / s0);
+    let object4 = if scaled >= 0 {
+        die4.saturating_add(term)
+    } else {
+        die4.saturating_sub(term)
+    };
+    // cK^2 = 100 mK^2
+    let object_mk = (object4.isqrt() * 100).isqrt();
+    (object_mk as i64 - ZERO_C_MK) as i32
+}
+
+/// For interacting with a TMP006 sensor
+pub struct DeviceTmp006<I2C>{
+    i2c: I2C,
+    address: u8,
+    s0: u32,
+}
+
+impl<I2C, E> DeviceTmp006<I2C>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+{
+    /// Checks the sensor's IDs at [`DEFAULT_ADDRESS`], then configures it with [`Config::default`]
+    pub fn new(i2c: I2C) -> Result<DeviceTmp006<I2C>, Error<E>>{
+        Self::with_address(i2c, DEFAULT_ADDRESS)
+    }
+
+    /// Checks the sensor's IDs, then configures it with [`Config::default`].
+    /// `address` is 0x40 to 0x47 depending on the ADR0 and ADR1 pins.
+    pub fn with_address(i2c: I2C, address: u8) -> Result<DeviceTmp006<I2C>, Error<E>>{
+        let mut device = DeviceTmp006 {
+            i2c,
+            address,
+            s0: DEFAULT_S0,
+        };
+        device.verify()?;
+        device.set_config(Config::default())?;
+        Ok(device)
+    }
+
+    /// Check that the device is a TMP006
+    pub fn verify(&mut self) -> Result<(), Error<E>>{
+        let manufacturer = self.read_register(REG_MANUFACTURER_ID)?;
+        if manufacturer != MANUFACTURER_ID {
+            return Err(Error::WrongManufacturer(manufacturer));
+        }
+        let device = self.read_register(REG_DEVICE_ID)?;
+        if device != DEVICE_ID {
+            return Err(Error::WrongDevice(device));
+        }
+        Ok(())
+    }
+
+    /// Read a 16-bit register, using a repeated start to set the register pointer.
+    /// Registers are sent most significant byte first.
+    pub fn read_register(&mut self, reg: u8) -> Result<u16, Error<E>>{
+        let mut read_buf : [u8;2] = [0,0];
+        self.i2c.write_read(self.address, &[reg], &mut read_buf).map_err(Error::I2c)?;
+        Ok(u16::from_be_bytes(read_buf))
+    }
+
+    /// Write a 16-bit register, most significant byte first
+    pub fn write_register(&mut self, reg: u8, val: u16) -> Result<(), Error<E>>{
+        let [msb, lsb] = val.to_be_bytes();
+        self.i2c.write(self.address, &[reg, msb, lsb]).map_err(Error::I2c)
+    }
+
+    pub fn config(&mut self) -> Result<Config, Error<E>>{
+        self.read_register(REG_CONFIG).map(Config::from_bits)
+    }
+
+    pub fn set_config(&mut self, config: Config) -> Result<(), Error<E>>{
+        self.write_register(REG_CONFIG, config.bits())
+    }
+
+    /// Read the configuration register, change it and write it back
+    pub fn modify_config<F: FnOnce(&mut Config)>(&mut self, f: F) -> Result<(), Error<E>>{
+        let mut config = self.config()?;
+        f(&mut config);
+        self.set_config(config)
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) -> Result<(), Error<E>>{
+        self.modify_config(|c| c.mode = mode)
+    }
+
+    pub fn set_conversion_rate(&mut self, rate: ConversionRate) -> Result<(), Error<E>>{
+        self.modify_config(|c| c.rate = rate)
+    }
+
+    /// Enable or disable the DRDY pin. It is open drain, so it needs a pull-up.
+    pub fn set_drdy_pin(&mut self, enabled: bool) -> Result<(), Error<E>>{
+        self.modify_config(|c| c.drdy_pin = enabled)
+    }
+
+    /// Return all registers to their power-on values
+    pub fn reset(&mut self) -> Result<(), Error<E>>{
+        self.write_register(REG_CONFIG, CFG_RST)
+    }
+
+    /// Whether a conversion finished since the results were last read
+    pub fn data_ready(&mut self) -> Result<bool, Error<E>>{
+        Ok(self.read_register(REG_CONFIG)? & CFG_DRDY != 0)
+    }
+
+    /// Thermopile voltage in nanovolts
+    pub fn sensor_voltage(&mut self) -> Result<i32, Error<E>>{
+        self.read_register(REG_VOLTAGE).map(voltage_to_nv)
+    }
+
+    /// Die temperature in thousandths of a degree Celsius
+    pub fn die_temperature(&mut self) -> Result<i32, Error<E>>{
+        self.read_register(REG_DIE_TEMP).map(die_temp_to_mc)
+    }
+
+    /// Temperature of the object in view, in thousandths of a degree Celsius.
+    /// See [`object_temperature`].
+    pub fn object_temperature(&mut self) -> Result<i32, Error<E>>{
+        let voltage = self.sensor_voltage()?;
+        let die = self.die_temperature()?;
+        Ok(object_temperature(voltage, die, self.s0))
+    }
+
+    /// The result, once a conversion has finished since the results were last read
+    pub fn poll_object_temperature(&mut self) -> nb::Result<i32, Error<E>>{
+        if !self.data_ready()? {
+            return Err(nb::Error::WouldBlock);
+        }
+        Ok(self.object_temperature()?)
+    }
+
+    /// Thermopile sensitivity in units of 1e-18, [`DEFAULT_S0`] unless changed
+    pub fn sensitivity(&self) -> u32{
+        self.s0
+    }
+
+    /// Set the thermopile sensitivity in units of 1e-18, typically between 50_000 and 70_000
+    pub fn set_sensitivity(&mut self, s0: u32){
+        self.s0 = s0;
+    }
+
+    /// Give back the I2C bus, e.g. to talk to other devices on it
+    pub fn release(self) -> I2C{
+        self.i2c
+    }
+}
+
+// A die at 25 C and no thermopile voltage: the offset correction alone puts the object at
+// 29.243 C with the floating point formula
+const _: () = {
+    assert!(die_temp_to_mc(0x0C80) == 25_000 && voltage_to_nv(0xFFFC) == -625);
+    let object = object_temperature(0, 25_000, 64_000);
+    assert!(object >= 29_240 && object <= 29_246);
+};
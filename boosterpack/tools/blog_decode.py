@@ -0,0 +1,251 @@
+#!/usr/bin/env python3
+"""Decode binary log frames (the `log-binary` feature, see src/log.rs) back into text.
+
+The format strings are read from the `.blog` entries in the ELF file that is running on the
+board. Anything that isn't a frame is passed through unchanged. A start byte only begins a frame
+if the frame after it is complete, has a known id and its CRC matches, so text containing those
+bytes (e.g. in UTF-8 characters) is passed through too.
+
+    python3 tools/blog_decode.py target/msp430-none-elf/debug/examples/demo --port /dev/ttyACM0
+    python3 tools/blog_decode.py target/msp430-none-elf/debug/examples/demo < capture.bin
+
+Reading from a serial port needs pyserial.
+"""
+
+import argparse
+import codecs
+import re
+import struct
+import sys
+
+FRAME_START = 0xB1
+FRAME_START_TIMESTAMP = 0xB2
+
+LEVELS = {1: "ERROR", 2: "WARN", 3: "INFO", 4: "DEBUG", 5: "TRACE"}
+
+# tag byte -> (struct format, size)
+INT_ARGS = {
+    0x01: ("<B", 1),
+    0x02: ("<H", 2),
+    0x03: ("<I", 4),
+    0x11: ("<b", 1),
+    0x12: ("<h", 2),
+    0x13: ("<i", 4),
+}
+TAG_BOOL = 0x20
+TAG_CHAR = 0x21
+TAG_STR = 0x30
+
+
+def crc8(data):
+    """CRC-8 with polynomial 0x07, as sent at the end of each frame."""
+    crc = 0
+    for byte in data:
+        crc ^= byte
+        for _ in range(8):
+            crc = ((crc << 1) ^ 0x07) & 0xFF if crc & 0x80 else (crc << 1) & 0xFF
+    return crc
+
+
+def read_entries(path):
+    """Map message ids to (tag, location, format string) using the `.blog` section contents."""
+    with open(path, "rb") as f:
+        elf = f.read()
+    if elf[:4] != b"\x7fELF":
+        sys.exit(f"{path} is not an ELF file")
+    is64 = elf[4] == 2
+    endian = "<" if elf[5] == 1 else ">"
+    if is64:
+        shoff, = struct.unpack_from(endian + "Q", elf, 0x28)
+        shentsize, shnum, shstrndx = struct.unpack_from(endian + "HHH", elf, 0x3A)
+        sh_fmt = endian + "IIQQQQIIQQ"
+    else:
+        shoff, = struct.unpack_from(endian + "I", elf, 0x20)
+        shentsize, shnum, shstrndx = struct.unpack_from(endian + "HHH", elf, 0x2E)
+        sh_fmt = endian + "IIIIIIIIII"
+
+    sections = [struct.unpack_from(sh_fmt, elf, shoff + i * shentsize) for i in range(shnum)]
+    shstr_off = sections[shstrndx][4]
+
+    def c_str(offset):
+        return elf[offset:elf.index(b"\0", offset)]
+
+    names = [c_str(shstr_off + sec[0]) for sec in sections]
+    if b".blog" not in names:
+        sys.exit(f"{path} has no .blog section, was it built with the log-binary feature?")
+    blog = sections[names.index(b".blog")]
+    addr, offset, size = blog[3], blog[4], blog[5]
+
+    # NUL terminated entries, the address of each being its id
+    entries = {}
+    pos = 0
+    while pos < size:
+        if elf[offset + pos] == 0:
+            pos += 1  # padding
+            continue
+        end = elf.index(b"\0", offset + pos, offset + size) - offset
+        parts = elf[offset + pos:offset + end].decode("utf-8", "replace").split("\x1f")
+        if len(parts) == 4 and parts[0] == "blog":
+            entries[(addr + pos) & 0xFFFF] = tuple(parts[1:])
+        pos = end + 1
+    return entries
+
+
+PLACEHOLDER = re.compile(r"\{\{|\}\}|\{([^{}]*)\}")
+
+
+def format_message(fmt, args):
+    """Apply Rust style `{}`/`{:spec}` placeholders to the decoded arguments."""
+    args = iter(args)
+
+    def replace(m):
+        if m.group(0) == "{{":
+            return "{"
+        if m.group(0) == "}}":
+            return "}"
+        spec = m.group(1).partition(":")[2]
+        value = next(args, "<missing>")
+        if isinstance(value, bool):
+            value = "true" if value else "false"
+        if spec.endswith("?"):
+            return repr(value)
+        try:
+            return format(value, spec)
+        except (ValueError, TypeError):
+            return str(value)
+
+    return PLACEHOLDER.sub(replace, fmt)
+
+
+class Decoder:
+    def __init__(self, entries, out):
+        self.entries = entries
+        self.out = out
+        self.buf = bytearray()
+        # text can be split anywhere, including inside a character
+        self.text = codecs.getincrementaldecoder("utf-8")("replace")
+
+    def feed(self, data):
+        self.buf += data
+        while self.buf:
+            start = self.buf[0]
+            if start not in (FRAME_START, FRAME_START_TIMESTAMP):
+                text_end = next((i for i, b in enumerate(self.buf)
+                                 if b in (FRAME_START, FRAME_START_TIMESTAMP)), len(self.buf))
+                self.out.write(self.text.decode(bytes(self.buf[:text_end])))
+                del self.buf[:text_end]
+                continue
+            used = self.parse_frame()
+            if used == 0:
+                break  # wait for more data
+            if used < 0:
+                # not a frame after all, pass the start byte on as text
+                self.out.write(self.text.decode(bytes(self.buf[:1])))
+                del self.buf[:1]
+                continue
+            del self.buf[:used]
+        self.out.flush()
+
+    def finish(self):
+        """Pass on whatever is left at the end of the input as text."""
+        self.out.write(self.text.decode(bytes(self.buf), final=True))
+        self.buf.clear()
+        self.out.flush()
+
+    def parse_frame(self):
+        """Decode the frame at the start of the buffer. Returns the bytes used, 0 if it is
+        incomplete or -1 if it isn't a valid frame."""
+        buf = self.buf
+        header = 5 if buf[0] == FRAME_START else 9
+        if len(buf) < header:
+            return 0
+        level = buf[1]
+        msg_id, = struct.unpack_from("<H", buf, 2)
+        if level not in LEVELS or msg_id not in self.entries:
+            return -1
+        ticks = struct.unpack_from("<I", buf, 4)[0] if buf[0] == FRAME_START_TIMESTAMP else None
+        num_args = buf[header - 1]
+        pos = header
+        args = []
+        for _ in range(num_args):
+            if pos >= len(buf):
+                return 0
+            tag = buf[pos]
+            pos += 1
+            if tag in INT_ARGS:
+                fmt, size = INT_ARGS[tag]
+                if pos + size > len(buf):
+                    return 0
+                args.append(struct.unpack_from(fmt, buf, pos)[0])
+                pos += size
+            elif tag == TAG_BOOL:
+                if pos + 1 > len(buf):
+                    return 0
+                args.append(bool(buf[pos]))
+                pos += 1
+            elif tag == TAG_CHAR:
+                if pos + 4 > len(buf):
+                    return 0
+                args.append(chr(struct.unpack_from("<I", buf, pos)[0]))
+                pos += 4
+            elif tag == TAG_STR:
+                if pos + 1 > len(buf) or pos + 1 + buf[pos] > len(buf):
+                    return 0
+                args.append(buf[pos + 1:pos + 1 + buf[pos]].decode("utf-8", "replace"))
+                pos += 1 + buf[pos]
+            else:
+                return -1
+        if pos >= len(buf):
+            return 0
+        if buf[pos] != crc8(buf[:pos]):
+            return -1
+        # flush any partial character, the frame interrupted the text
+        self.out.write(self.text.decode(b"", final=True))
+
+        line = ""
+        if ticks is not None:
+            line += f"[{ticks:8}] "
+        line += f"{LEVELS[level]:<5} "
+        tag, _location, fmt = self.entries[msg_id]
+        if tag:
+            line += f"[{tag}] "
+        line += format_message(fmt, args)
+        self.out.write(line + "\n")
+        return pos + 1
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__, formatter_class=argparse.RawDescriptionHelpFormatter)
+    parser.add_argument("elf", help="ELF file of the program running on the board")
+    parser.add_argument("--port", help="serial port to read from, instead of stdin")
+    parser.add_argument("--baud", type=int, default=9600)
+    parser.add_argument("--list", action="store_true", help="print the message table and exit")
+    args = parser.parse_args()
+
+    entries = read_entries(args.elf)
+    if args.list:
+        for msg_id, (tag, location, fmt) in sorted(entries.items()):
+            print(f"{msg_id:5} {location} [{tag}] {fmt}")
+        return
+
+    decoder = Decoder(entries, sys.stdout)
+    if args.port:
+        import serial
+        with serial.Serial(args.port, args.baud) as port:
+            while True:
+                decoder.feed(port.read(max(1, port.in_waiting)))
+    else:
+        stdin = sys.stdin.buffer
+        while True:
+            data = stdin.read1(256)
+            if not data:
+                break
+            decoder.feed(data)
+        decoder.finish()
+
+
+if __name__ == "__main__":
+    try:
+        main()
+    except KeyboardInterrupt:
+        pass